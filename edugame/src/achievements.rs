@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/*
+ * Logros (insignias). bonus y penalty son solo contadores, con esto se reconocen cosas puntuales:
+ * asistencia perfecta, sacarse un 7.0, llegar a nivel 10, etc.
+ * Se revisan despues de cada scrapeo, juego y compra, y una vez desbloqueados quedan para siempre con la fecha
+ */
+
+// Lo que se necesita saber del estudiante para revisar los logros, se arma en main.rs con Student::achievement_facts
+#[derive(Debug, Clone, Default)]
+pub struct Facts {
+	pub level: u16,
+	pub best_attendance: f32,		// La mejor asistencia entre todos los cursos
+	pub best_grade: f32,			// La mejor nota de cualquier evaluacion
+	pub improving_streak: usize,	// La racha mas larga de notas seguidas que van subiendo dentro de un curso
+	pub slots_jackpot: bool,		// Solo es true en la jugada de slots que saco 3 iguales
+}
+
+pub struct Achievement {
+	pub id: &'static str,
+	pub name: &'static str,
+	pub description: &'static str,
+	pub icon: &'static str,
+	unlocked_by: fn(&Facts) -> bool,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+	Achievement {
+		id: "perfect_attendance",
+		name: "Asistencia perfecta",
+		description: "Tener 100% de asistencia en un curso",
+		icon: "📅",
+		unlocked_by: |f| f.best_attendance >= 100.0,
+	},
+	Achievement {
+		id: "seven",
+		name: "Un 7.0",
+		description: "Sacarse un 7.0 en una evaluación",
+		icon: "⭐",
+		unlocked_by: |f| f.best_grade >= 7.0,
+	},
+	Achievement {
+		id: "level_10",
+		name: "Nivel 10",
+		description: "Llegar a nivel 10",
+		icon: "🔟",
+		unlocked_by: |f| f.level >= 10,
+	},
+	Achievement {
+		id: "first_jackpot",
+		name: "Jackpot",
+		description: "Sacar 3 iguales en slots por primera vez",
+		icon: "🎰",
+		unlocked_by: |f| f.slots_jackpot,
+	},
+	Achievement {
+		id: "improving_streak",
+		name: "En racha",
+		description: "Tres notas seguidas mejorando en un mismo curso",
+		icon: "📈",
+		unlocked_by: |f| f.improving_streak >= 3,
+	},
+];
+
+// Una insignia como se entrega en la API, unlocked_at es None si todavia no se desbloquea
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Badge {
+	pub id: String,
+	pub name: String,
+	pub description: String,
+	pub icon: String,
+	pub unlocked_at: Option<DateTime<Utc>>,
+}
+
+impl Achievement {
+	fn badge(&self, unlocked_at: Option<DateTime<Utc>>) -> Badge {
+		Badge {
+			id: self.id.to_string(),
+			name: self.name.to_string(),
+			description: self.description.to_string(),
+			icon: self.icon.to_string(),
+			unlocked_at,
+		}
+	}
+}
+
+// Desbloquea lo que se cumpla y no estaba desbloqueado, devuelve solo los nuevos para avisarle al estudiante
+pub fn evaluate(unlocked: &mut HashMap<String, DateTime<Utc>>, facts: &Facts, now: DateTime<Utc>) -> Vec<Badge> {
+	let mut new_badges = vec![];
+	for achievement in ACHIEVEMENTS {
+		if !unlocked.contains_key(achievement.id) && (achievement.unlocked_by)(facts) {
+			unlocked.insert(achievement.id.to_string(), now);
+			new_badges.push(achievement.badge(Some(now)));
+		}
+	}
+	new_badges
+}
+
+// Todas las insignias separadas en desbloqueadas (mas antigua primero) y bloqueadas
+pub fn badges(unlocked: &HashMap<String, DateTime<Utc>>) -> (Vec<Badge>, Vec<Badge>) {
+	let (mut done, pending): (Vec<Badge>, Vec<Badge>) = ACHIEVEMENTS.iter()
+		.map(|achievement| achievement.badge(unlocked.get(achievement.id).copied()))
+		.partition(|badge| badge.unlocked_at.is_some());
+	done.sort_by_key(|badge| badge.unlocked_at);
+	(done, pending)
+}
+
+// Racha mas larga de notas que suben una tras otra, [4.0, 5.0, 6.0] = 3
+pub fn longest_improving_run(grades: &[f32]) -> usize {
+	let mut longest = grades.len().min(1);
+	let mut current = longest;
+	for pair in grades.windows(2) {
+		current = if pair[1] > pair[0] { current + 1 } else { 1 };
+		longest = longest.max(current);
+	}
+	longest
+}
@@ -0,0 +1,25 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+
+/*
+ * Las rutas de administracion (cerrar temporada, etc) piden el header X-Admin-Token con el valor de EDUGAME_ADMIN_TOKEN.
+ * Si la variable no esta definida esas rutas quedan cerradas para todos
+ */
+
+pub struct Admin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+	type Error = &'static str;
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+		let Ok(expected) = std::env::var("EDUGAME_ADMIN_TOKEN") else {
+			return Outcome::Error((Status::Forbidden, "administracion deshabilitada"));
+		};
+		match request.headers().get_one("X-Admin-Token") {
+			Some(token) if !expected.is_empty() && token == expected => Outcome::Success(Admin),
+			_ => Outcome::Error((Status::Forbidden, "token de administrador invalido")),
+		}
+	}
+}
@@ -0,0 +1,128 @@
+use rocket::http::{ContentType, CookieJar, Status};
+use rocket::response::content::RawHtml;
+use rocket::serde::{Deserialize, json::{Json, serde_json}};
+use rocket::{get, post, routes, launch, form::Form, State};
+use rocket::tokio::sync::Mutex;
+use rand::Rng;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/*
+ * Ucampus de mentira para probar EduGame sin internet y sin credenciales reales.
+ * Hace lo mismo que el de verdad: GET / entrega la cookie _ucampus, POST /auth/api revisa usuario y contraseña,
+ * y despues sirve el portal, notas/alumno y asistencias2 desde los html de fixtures/ucampus.
+ *
+ * cargo run --bin mock_ucampus
+ * EDUGAME_UCAMPUS_URL=http://127.0.0.1:8001 cargo run
+ */
+
+const NO_PERMISSION_PAGE: &str = "<html><body><h2>No tienes permisos para ver esta página</h2></body></html>";
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Account {
+	username: String,
+	password: String,
+	dir: String,					// Carpeta dentro de fixtures con portal.html y una carpeta por curso
+	sections: HashMap<String, u8>,	// codigo del curso -> seccion en la que esta inscrito
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Accounts {
+	accounts: Vec<Account>,
+}
+
+struct MockState {
+	fixtures: PathBuf,
+	accounts: Vec<Account>,
+	sessions: Mutex<HashMap<String, Option<String>>>, // valor de la cookie -> usuario logueado (None si solo pidio la cookie)
+}
+
+impl MockState {
+	async fn logged_account(&self, cookies: &CookieJar<'_>) -> Option<&Account> {
+		let sess = cookies.get("_ucampus")?.value().to_string();
+		let username = self.sessions.lock().await.get(&sess).cloned()??;
+		self.accounts.iter().find(|a| a.username == username)
+	}
+
+	fn read_fixture(&self, account: &Account, parts: &[&str]) -> Option<String> {
+		let mut path = self.fixtures.join(&account.dir);
+		for part in parts {
+			path = path.join(part);
+		}
+		std::fs::read_to_string(path).ok()
+	}
+}
+
+#[derive(Debug, rocket::form::FromForm)]
+struct AuthForm {
+	_sess: String,
+	username: String,
+	password: String,
+}
+
+#[get("/")]
+async fn home(cookies: &CookieJar<'_>, state: &State<MockState>) -> RawHtml<&'static str> {
+	let sess = format!("{:016x}", rand::thread_rng().r#gen::<u64>());
+	state.sessions.lock().await.insert(sess.clone(), None);
+	cookies.add(("_ucampus", sess));
+	RawHtml("<html><body>U-Campus (mock)</body></html>")
+}
+
+#[post("/auth/api", data = "<form>")]
+async fn auth_api(form: Form<AuthForm>, host: &rocket::http::uri::Host<'_>, state: &State<MockState>) -> Json<serde_json::Value> {
+	let mut sessions = state.sessions.lock().await;
+	let valid_session = sessions.contains_key(&form._sess);
+	let valid_login = state.accounts.iter().any(|a| a.username == form.username && a.password == form.password);
+
+	if !valid_session || !valid_login {
+		return Json(serde_json::json!({ "status": 401, "u": null, "m": "Usuario o contraseña incorrectos" }));
+	}
+
+	sessions.insert(form._sess.clone(), Some(form.username.clone()));
+	Json(serde_json::json!({ "status": 200, "u": format!("http://{}/portal", host) }))
+}
+
+#[get("/portal")]
+async fn portal(cookies: &CookieJar<'_>, state: &State<MockState>) -> Result<RawHtml<String>, Status> {
+	let account = state.logged_account(cookies).await.ok_or(Status::Unauthorized)?;
+	state.read_fixture(account, &["portal.html"]).map(RawHtml).ok_or(Status::NotFound)
+}
+
+// Si la seccion no es la del alumno se responde igual que ucampus, con 200 y el mensaje de permisos
+async fn course_page(cookies: &CookieJar<'_>, state: &MockState, code: &str, section: u8, file: &str) -> Result<(ContentType, String), Status> {
+	let account = state.logged_account(cookies).await.ok_or(Status::Unauthorized)?;
+	if account.sections.get(code) != Some(&section) {
+		return Ok((ContentType::HTML, NO_PERMISSION_PAGE.to_string()));
+	}
+	let page = state.read_fixture(account, &[code, file]).ok_or(Status::NotFound)?;
+	Ok((ContentType::HTML, page))
+}
+
+#[get("/uah/<_year>/<_term>/<code>/<section>/notas/alumno")]
+async fn notas(_year: u16, _term: u8, code: &str, section: u8, cookies: &CookieJar<'_>, state: &State<MockState>) -> Result<(ContentType, String), Status> {
+	course_page(cookies, state, code, section, "notas.html").await
+}
+
+#[get("/uah/<_year>/<_term>/<code>/<section>/asistencias2")]
+async fn asistencias(_year: u16, _term: u8, code: &str, section: u8, cookies: &CookieJar<'_>, state: &State<MockState>) -> Result<(ContentType, String), Status> {
+	course_page(cookies, state, code, section, "asistencias2.html").await
+}
+
+#[launch]
+fn rocket() -> _ {
+	let fixtures = PathBuf::from(std::env::var("MOCK_UCAMPUS_FIXTURES").unwrap_or("fixtures/ucampus".to_string()));
+	let raw = std::fs::read_to_string(fixtures.join("accounts.json")).expect("No se pudo leer accounts.json de los fixtures");
+	let accounts: Accounts = serde_json::from_str(&raw).expect("accounts.json mal formado");
+
+	let port: u16 = std::env::var("MOCK_UCAMPUS_PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(8001);
+
+	rocket::custom(rocket::Config::figment().merge(("port", port)))
+		.manage(MockState {
+			fixtures,
+			accounts: accounts.accounts,
+			sessions: Mutex::new(HashMap::new()),
+		})
+		.mount("/", routes![home, auth_api, portal, notas, asistencias])
+}
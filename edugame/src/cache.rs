@@ -0,0 +1,235 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use rocket::serde::{Deserialize, Serialize};
+use rocket::tokio::sync::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ucampus::{CourseData, FailedCourse, ScrapedData};
+
+/*
+ * Ultimo scrapeo bueno de cada estudiante, si ucampus se cae o se demora se sigue mostrando lo ultimo que se saco
+ * con la fecha, para que el estudiante sepa que esos datos no son de ahora
+ */
+
+// Si ucampus ni siquiera deja loguear, solo se usa el cache si el ultimo login bueno fue hace menos que esto
+pub const MAX_OFFLINE_AGE_HOURS: i64 = 72;
+
+#[derive(Debug, Clone)]
+pub struct CachedCourse {
+	pub data: CourseData,
+	pub fetched_at: DateTime<Utc>,
+}
+
+// Vueltas de SHA-256, para que probar contraseñas contra el hash guardado no sea gratis
+const PASSWORD_ROUNDS: u32 = 10_000;
+
+/*
+ * La ultima contraseña con la que ucampus dejo entrar, con sal y hasheada. Si ucampus esta caido no hay como validar
+ * la contraseña, asi que el cache solo se entrega si coincide con esta
+ */
+#[derive(Clone)]
+pub struct PasswordHash {
+	salt: [u8; 16],
+	hash: [u8; 32],
+}
+
+impl std::fmt::Debug for PasswordHash {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("PasswordHash(..)")
+	}
+}
+
+impl PasswordHash {
+	pub fn new(password: &str) -> Self {
+		let mut salt = [0u8; 16];
+		rand::thread_rng().fill_bytes(&mut salt);
+		PasswordHash { salt, hash: hash_password(&salt, password) }
+	}
+
+	pub fn matches(&self, password: &str) -> bool {
+		let candidate = hash_password(&self.salt, password);
+		// Se comparan todos los bytes siempre, sin cortar en el primero distinto
+		candidate.iter().zip(self.hash.iter()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+	}
+}
+
+fn hash_password(salt: &[u8; 16], password: &str) -> [u8; 32] {
+	let mut hash: [u8; 32] = Sha256::new().chain_update(salt).chain_update(password.as_bytes()).finalize().into();
+	for _ in 1..PASSWORD_ROUNDS {
+		hash = Sha256::new().chain_update(salt).chain_update(hash).finalize().into();
+	}
+	hash
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CachedScrape {
+	pub name: Option<String>,
+	pub courses: HashMap<String, CachedCourse>,	// codigo del curso -> ultimo dato bueno
+	pub last_login: Option<DateTime<Utc>>,
+	pub password: Option<PasswordHash>,			// De last_login, para el modo sin ucampus
+}
+
+// Curso que se mostro desde el cache, se manda al front para decir de cuando es
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct StaleCourse {
+	pub code: String,
+	pub fetched_at: DateTime<Utc>,
+	pub age_seconds: i64,
+}
+
+pub type ScrapeCache = Arc<RwLock<HashMap<String, CachedScrape>>>;
+
+impl CachedScrape {
+	// Guarda lo que llego bien en este scrapeo, los cursos que fallaron se quedan con su dato anterior
+	pub fn store(&mut self, scraped: &ScrapedData, password: &str, now: DateTime<Utc>) {
+		if scraped.name.is_some() {
+			self.name = scraped.name.clone();
+		}
+		for course in &scraped.courses {
+			self.courses.insert(course.code.clone(), CachedCourse { data: course.clone(), fetched_at: now });
+		}
+		self.last_login = Some(now);
+		if !self.password.as_ref().is_some_and(|hash| hash.matches(password)) {
+			self.password = Some(PasswordHash::new(password));
+		}
+	}
+
+	// El login fue bueno pero algunos cursos fallaron, se rellenan con lo que haya en cache (sin importar la antiguedad)
+	pub fn fill_failed(&self, scraped: &mut ScrapedData, now: DateTime<Utc>) -> Vec<StaleCourse> {
+		let mut stale = vec![];
+		let mut still_failed: Vec<FailedCourse> = vec![];
+
+		for failed in scraped.failed.drain(..) {
+			match self.courses.get(&failed.code) {
+				Some(cached) => {
+					scraped.courses.push(cached.data.clone());
+					stale.push(stale_course(&failed.code, cached, now));
+				}
+				None => still_failed.push(failed),
+			}
+		}
+
+		scraped.failed = still_failed;
+		if scraped.name.is_none() {
+			scraped.name = self.name.clone();
+		}
+		stale
+	}
+
+	// Ucampus no respondio ni para el login, se entrega todo el cache si el ultimo login bueno es reciente y la contraseña es la de ese login
+	pub fn offline_copy(&self, password: &str, now: DateTime<Utc>) -> Option<(ScrapedData, Vec<StaleCourse>)> {
+		if !self.password.as_ref().is_some_and(|hash| hash.matches(password)) {
+			return None;
+		}
+		let last_login = self.last_login?;
+		if now - last_login > Duration::hours(MAX_OFFLINE_AGE_HOURS) || self.courses.is_empty() {
+			return None;
+		}
+
+		let mut codes: Vec<&String> = self.courses.keys().collect();
+		codes.sort();
+
+		let scraped = ScrapedData {
+			name: self.name.clone(),
+			courses: codes.iter().map(|code| self.courses[*code].data.clone()).collect(),
+			failed: vec![],
+			first_error: None,
+		};
+		let stale = codes.iter().map(|code| stale_course(code, &self.courses[*code], now)).collect();
+		Some((scraped, stale))
+	}
+}
+
+fn stale_course(code: &str, cached: &CachedCourse, now: DateTime<Utc>) -> StaleCourse {
+	StaleCourse {
+		code: code.to_string(),
+		fetched_at: cached.fetched_at,
+		age_seconds: (now - cached.fetched_at).num_seconds(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn course(code: &str, attendance: f32) -> CourseData {
+		CourseData { code: code.to_string(), grades: vec![], attendance, sessions: vec![] }
+	}
+
+	fn scraped(courses: Vec<CourseData>, failed: &[&str]) -> ScrapedData {
+		ScrapedData {
+			name: Some("Demo".to_string()),
+			courses,
+			failed: failed.iter().map(|code| FailedCourse { code: code.to_string(), name: code.to_string(), reason: "caido".to_string() }).collect(),
+			first_error: None,
+		}
+	}
+
+	fn login_at(hours_ago: i64) -> DateTime<Utc> {
+		Utc::now() - Duration::hours(hours_ago)
+	}
+
+	#[test]
+	fn offline_copy_requires_matching_password() {
+		let mut cache = CachedScrape::default();
+		cache.store(&scraped(vec![course("CSI0165", 70.0)], &[]), "demo1234", login_at(1));
+		assert!(cache.offline_copy("otra", Utc::now()).is_none());
+		assert!(cache.offline_copy("", Utc::now()).is_none());
+		let (offline, stale) = cache.offline_copy("demo1234", Utc::now()).unwrap();
+		assert_eq!(offline.courses.len(), 1);
+		assert_eq!(stale[0].code, "CSI0165");
+	}
+
+	#[test]
+	fn offline_copy_without_password_is_refused() {
+		let mut cache = CachedScrape::default();
+		cache.store(&scraped(vec![course("CSI0165", 70.0)], &[]), "demo1234", login_at(1));
+		cache.password = None;
+		assert!(cache.offline_copy("demo1234", Utc::now()).is_none());
+	}
+
+	#[test]
+	fn offline_copy_refuses_old_login() {
+		let mut cache = CachedScrape::default();
+		cache.store(&scraped(vec![course("CSI0165", 70.0)], &[]), "demo1234", login_at(MAX_OFFLINE_AGE_HOURS + 1));
+		assert!(cache.offline_copy("demo1234", Utc::now()).is_none());
+
+		cache.store(&scraped(vec![course("CSI0165", 70.0)], &[]), "demo1234", login_at(MAX_OFFLINE_AGE_HOURS - 1));
+		assert!(cache.offline_copy("demo1234", Utc::now()).is_some());
+	}
+
+	#[test]
+	fn store_rehashes_on_password_change() {
+		let mut cache = CachedScrape::default();
+		cache.store(&scraped(vec![course("CSI0165", 70.0)], &[]), "vieja", login_at(2));
+		let salt = cache.password.as_ref().unwrap().salt;
+
+		// Misma contraseña, se deja el hash que estaba
+		cache.store(&scraped(vec![course("CSI0165", 70.0)], &[]), "vieja", login_at(1));
+		assert_eq!(cache.password.as_ref().unwrap().salt, salt);
+
+		cache.store(&scraped(vec![course("CSI0165", 70.0)], &[]), "nueva", Utc::now());
+		let hash = cache.password.as_ref().unwrap();
+		assert!(hash.matches("nueva"));
+		assert!(!hash.matches("vieja"));
+		assert!(cache.offline_copy("vieja", Utc::now()).is_none());
+	}
+
+	#[test]
+	fn fill_failed_only_fills_failed_courses() {
+		let mut cache = CachedScrape::default();
+		cache.store(&scraped(vec![course("CSI0165", 70.0), course("CSI0168", 91.0)], &[]), "demo1234", login_at(1));
+
+		// CSI0165 llego fresco, CSI0168 fallo y esta en cache, CSI0169 fallo y nunca se guardo
+		let mut now = scraped(vec![course("CSI0165", 75.0)], &["CSI0168", "CSI0169"]);
+		let stale = cache.fill_failed(&mut now, Utc::now());
+
+		let attendance: Vec<(&str, f32)> = now.courses.iter().map(|c| (c.code.as_str(), c.attendance)).collect();
+		assert_eq!(attendance, [("CSI0165", 75.0), ("CSI0168", 91.0)]);
+		assert_eq!(stale.iter().map(|c| c.code.as_str()).collect::<Vec<_>>(), ["CSI0168"]);
+		assert_eq!(now.failed.iter().map(|c| c.code.as_str()).collect::<Vec<_>>(), ["CSI0169"]);
+	}
+}
@@ -0,0 +1,153 @@
+use rocket::serde::{Deserialize, Serialize, json::serde_json};
+use std::collections::HashSet;
+
+/*
+ * Catalogo de cursos, antes teniamos 2025/1 y los cuatro CSI metidos a mano dentro del scraper,
+ * ahora se leen de un archivo json al iniciar, asi cada semestre nuevo es cambiar el archivo y no el codigo
+ */
+
+pub const DEFAULT_CATALOG_PATH: &str = "config/catalog.json";
+// Para los cursos que aparecen en el portal pero no estan en el catalogo. No limita las notas que se sacan, solo se compara en el log de debug
+pub const DEFAULT_EXPECTED_EVALUATIONS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Course {
+	pub code: String,				// CSI0168, el mismo que sale en la url de ucampus
+	pub name: String,				// Nombre que se muestra en la pagina
+	pub year: u16,
+	pub term: u8,					// 1 o 2, el semestre
+	pub sections: Vec<u8>,			// Secciones que se prueban en orden
+	pub expected_evaluations: usize, // Cuantas notas se esperan, solo para el log si salen otras
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CourseCatalog {
+	pub courses: Vec<Course>,
+}
+
+impl Course {
+	// La parte de la url que va despues del dominio, ej: uah/2025/1/CSI0168/2
+	pub fn path_for_section(&self, section: u8) -> String {
+		format!("uah/{}/{}/{}/{}", self.year, self.term, self.code, section)
+	}
+}
+
+impl CourseCatalog {
+	// Lee el catalogo, la ruta se puede cambiar con EDUGAME_CATALOG
+	pub fn load() -> Result<Self, String> {
+		let path = std::env::var("EDUGAME_CATALOG").unwrap_or(DEFAULT_CATALOG_PATH.to_string());
+		Self::from_file(&path)
+	}
+
+	pub fn from_file(path: &str) -> Result<Self, String> {
+		let raw = std::fs::read_to_string(path)
+			.map_err(|e| format!("No se pudo leer el catalogo {}: {}", path, e))?;
+		let catalog: CourseCatalog = serde_json::from_str(&raw)
+			.map_err(|e| format!("Catalogo {} mal formado: {}", path, e))?;
+		catalog.validate()?;
+		Ok(catalog)
+	}
+
+	// Año y semestre del catalogo, cada catalogo es un solo semestre (una temporada)
+	pub fn term(&self) -> (u16, u8) {
+		self.courses.first().map(|c| (c.year, c.term)).unwrap_or_default()
+	}
+
+	// Revisamos lo basico para no enterarnos recien cuando alguien se loguea
+	pub fn validate(&self) -> Result<(), String> {
+		if self.courses.is_empty() {
+			return Err("El catalogo no tiene cursos".to_string());
+		}
+
+		let mut seen = HashSet::new();
+		for course in &self.courses {
+			if course.code.trim().is_empty() {
+				return Err("Hay un curso sin codigo en el catalogo".to_string());
+			}
+			if !seen.insert(course.code.as_str()) {
+				return Err(format!("El curso {} esta repetido en el catalogo", course.code));
+			}
+			if course.sections.is_empty() {
+				return Err(format!("El curso {} no tiene secciones", course.code));
+			}
+			if course.term == 0 || course.term > 2 {
+				return Err(format!("El curso {} tiene un semestre invalido: {}", course.code, course.term));
+			}
+			if course.expected_evaluations == 0 {
+				return Err(format!("El curso {} espera 0 evaluaciones", course.code));
+			}
+			if (course.year, course.term) != self.term() {
+				return Err(format!("El curso {} es de otro semestre, el catalogo tiene que ser de un solo semestre", course.code));
+			}
+		}
+		Ok(())
+	}
+}
+
+/*
+ * Despues del login el portal de ucampus lista los cursos del alumno con links del estilo
+ * <a href="/uah/2025/1/CSI0168/2/">Electivo de Especialidad I</a>, de ahí sacamos codigo y seccion.
+ * Solo se toman los del semestre que esta en el catalogo, y si el curso esta en el catalogo se usa su nombre y sus evaluaciones
+ */
+pub fn discover_courses(portal_html: &str, catalog: &CourseCatalog) -> Vec<Course> {
+	let mut found: Vec<Course> = vec![];
+	let mut rest = portal_html;
+
+	while let Some(idx) = rest.find("uah/") {
+		let after = &rest[idx + 4..];
+		rest = after;
+
+		let mut parts = after.splitn(5, '/');
+		let (Some(year), Some(term), Some(code), Some(section)) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+			continue;
+		};
+		// La seccion puede venir pegada al final del href (.../CSI0168/2">), nos quedamos con los digitos del inicio
+		let section: String = section.chars().take_while(|c| c.is_ascii_digit()).collect();
+		let (Ok(year), Ok(term), Ok(section)) = (year.parse::<u16>(), term.parse::<u8>(), section.parse::<u8>()) else {
+			continue;
+		};
+		if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+			continue;
+		}
+		if !catalog.courses.iter().any(|c| c.year == year && c.term == term) {
+			continue; // Cursos de otros semestres no nos sirven
+		}
+		if found.iter().any(|c| c.code == code) {
+			continue;
+		}
+
+		let known = catalog.courses.iter().find(|c| c.code == code);
+		found.push(Course {
+			code: code.to_string(),
+			name: known.map(|c| c.name.clone()).or_else(|| link_text(after)).unwrap_or(code.to_string()),
+			year,
+			term,
+			sections: vec![section],
+			expected_evaluations: known.map(|c| c.expected_evaluations).unwrap_or(DEFAULT_EXPECTED_EVALUATIONS),
+		});
+	}
+
+	found
+}
+
+// El texto del <a> que viene despues del href, sin los tags que tenga adentro
+fn link_text(after_href: &str) -> Option<String> {
+	let start = after_href.find('>')? + 1;
+	let end = after_href[start..].find("</a>")? + start;
+
+	let mut text = String::new();
+	let mut in_tag = false;
+	for c in after_href[start..end].chars() {
+		match c {
+			'<' => in_tag = true,
+			'>' => in_tag = false,
+			_ if !in_tag => text.push(c),
+			_ => {}
+		}
+	}
+
+	let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+	if text.is_empty() { None } else { Some(text) }
+}
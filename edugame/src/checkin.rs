@@ -0,0 +1,160 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use rocket::serde::{Deserialize, Serialize};
+
+/*
+ * Check-in diario, una razon para entrar a EduGame aparte del scrapeo. Cada dia seguido que el estudiante marca
+ * sube la racha y con ella el premio. Los dias se cuentan en hora de Chile, no en UTC, asi marcar a las 22:00 cuenta para hoy.
+ * Los protectores de racha se compran en la tienda y cubren un dia sin marcar cada uno
+ */
+
+pub const TIMEZONE: Tz = chrono_tz::America::Santiago;
+
+// El dia en Chile que corresponde a un instante
+pub fn local_day(at: DateTime<Utc>) -> NaiveDate {
+	at.with_timezone(&TIMEZONE).date_naive()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Streak {
+	pub current: u32,
+	pub longest: u32,
+	pub last_check_in: Option<NaiveDate>,
+	pub freezes: u32,		// Protectores sin usar
+}
+
+pub struct CheckIn {
+	pub streak: u32,
+	pub freezes_used: u32,
+	pub broken: bool,		// Se perdio una racha anterior
+}
+
+impl Streak {
+	// Dias sin marcar entre el ultimo check-in y hoy
+	fn missed_days(&self, today: NaiveDate) -> Option<u32> {
+		self.last_check_in.map(|last| (today - last).num_days().max(1) as u32 - 1)
+	}
+
+	/*
+	 * Marca el dia. Si falto algun dia se gastan protectores para cubrirlos, si no alcanzan la racha parte de nuevo
+	 * y los protectores se guardan para la proxima
+	 */
+	pub fn check_in(&mut self, today: NaiveDate) -> Result<CheckIn, String> {
+		if self.last_check_in.is_some_and(|last| last >= today) {
+			return Err("Ya hiciste check-in hoy, vuelve mañana".to_string());
+		}
+		let mut freezes_used = 0;
+		let mut broken = false;
+		match self.missed_days(today) {
+			Some(0) => self.current += 1,
+			Some(missed) if missed <= self.freezes => {
+				self.freezes -= missed;
+				freezes_used = missed;
+				self.current += 1;
+			},
+			Some(_) => {
+				broken = self.current > 0;
+				self.current = 1;
+			},
+			None => self.current = 1,
+		}
+		self.longest = self.longest.max(self.current);
+		self.last_check_in = Some(today);
+		Ok(CheckIn { streak: self.current, freezes_used, broken })
+	}
+
+	// La racha que sigue viva hoy (con los protectores que tiene), 0 si ya se perdio
+	pub fn active(&self, today: NaiveDate) -> u32 {
+		match self.missed_days(today) {
+			Some(missed) if missed <= self.freezes => self.current,
+			_ => 0,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::TimeZone;
+
+	fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+		NaiveDate::from_ymd_opt(y, m, d).unwrap()
+	}
+
+	fn streak_on(last: NaiveDate, current: u32, freezes: u32) -> Streak {
+		Streak { current, longest: current, last_check_in: Some(last), freezes }
+	}
+
+	#[test]
+	fn first_check_in_starts_streak() {
+		let mut streak = Streak::default();
+		let outcome = streak.check_in(day(2025, 5, 10)).unwrap();
+		assert_eq!((outcome.streak, outcome.freezes_used, outcome.broken), (1, 0, false));
+	}
+
+	#[test]
+	fn same_day_is_rejected() {
+		let mut streak = streak_on(day(2025, 5, 10), 4, 0);
+		assert!(streak.check_in(day(2025, 5, 10)).is_err());
+		assert_eq!(streak.current, 4);
+	}
+
+	#[test]
+	fn next_day_grows_streak() {
+		let mut streak = streak_on(day(2025, 5, 10), 4, 1);
+		let outcome = streak.check_in(day(2025, 5, 11)).unwrap();
+		assert_eq!((outcome.streak, outcome.freezes_used), (5, 0));
+		assert_eq!(streak.freezes, 1);
+		assert_eq!(streak.longest, 5);
+	}
+
+	#[test]
+	fn missed_day_covered_by_freeze() {
+		let mut streak = streak_on(day(2025, 5, 10), 4, 1);
+		assert_eq!(streak.active(day(2025, 5, 12)), 4);
+		let outcome = streak.check_in(day(2025, 5, 12)).unwrap();
+		assert_eq!((outcome.streak, outcome.freezes_used, outcome.broken), (5, 1, false));
+		assert_eq!(streak.freezes, 0);
+	}
+
+	#[test]
+	fn not_enough_freezes_breaks_streak() {
+		// Faltaron dos dias y hay un solo protector, no se gasta y la racha parte de nuevo
+		let mut streak = streak_on(day(2025, 5, 10), 4, 1);
+		assert_eq!(streak.active(day(2025, 5, 13)), 0);
+		let outcome = streak.check_in(day(2025, 5, 13)).unwrap();
+		assert_eq!((outcome.streak, outcome.freezes_used, outcome.broken), (1, 0, true));
+		assert_eq!(streak.freezes, 1);
+		assert_eq!(streak.longest, 4);
+	}
+
+	#[test]
+	fn local_day_uses_santiago_time() {
+		// 02:00 UTC todavia es el dia anterior en Chile
+		assert_eq!(local_day(Utc.with_ymd_and_hms(2025, 5, 11, 2, 0, 0).unwrap()), day(2025, 5, 10));
+		assert_eq!(local_day(Utc.with_ymd_and_hms(2025, 5, 11, 4, 0, 0).unwrap()), day(2025, 5, 11));
+	}
+
+	#[test]
+	fn local_day_around_dst_end() {
+		// El 6 de abril de 2025 a las 00:00 (-03) se vuelve a las 23:00 (-04) del 5, ese dia dura 25 horas
+		assert_eq!(local_day(Utc.with_ymd_and_hms(2025, 4, 6, 2, 30, 0).unwrap()), day(2025, 4, 5));
+		assert_eq!(local_day(Utc.with_ymd_and_hms(2025, 4, 6, 3, 30, 0).unwrap()), day(2025, 4, 5));
+		assert_eq!(local_day(Utc.with_ymd_and_hms(2025, 4, 6, 4, 30, 0).unwrap()), day(2025, 4, 6));
+	}
+
+	#[test]
+	fn streak_across_dst_start() {
+		// El 7 de septiembre de 2025 a las 00:00 (-04) se salta a la 01:00 (-03), ese dia dura 23 horas
+		let saturday_night = Utc.with_ymd_and_hms(2025, 9, 7, 3, 30, 0).unwrap(); // 23:30 del 6
+		let sunday_night = Utc.with_ymd_and_hms(2025, 9, 8, 2, 30, 0).unwrap(); // 23:30 del 7, 23 horas despues
+		assert_eq!(local_day(saturday_night), day(2025, 9, 6));
+		assert_eq!(local_day(sunday_night), day(2025, 9, 7));
+
+		let mut streak = Streak::default();
+		streak.check_in(local_day(saturday_night)).unwrap();
+		let outcome = streak.check_in(local_day(sunday_night)).unwrap();
+		assert_eq!((outcome.streak, outcome.freezes_used), (2, 0));
+	}
+}
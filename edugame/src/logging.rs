@@ -0,0 +1,93 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request, Response};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/*
+ * Logs con niveles en vez del "Super debug information" con println que imprimia el correo, las notas y las asistencias.
+ * Por defecto los correos y las notas salen ocultos, EDUGAME_LOG_SENSITIVE=1 los muestra para depurar en local.
+ * Las contraseñas no pasan nunca por aqui (LoginForm no las imprime ni en Debug) asi que no hay forma de loguearlas.
+ * El nivel se cambia con EDUGAME_LOG, por ejemplo EDUGAME_LOG=edugame=debug
+ */
+
+static SENSITIVE: OnceLock<bool> = OnceLock::new();
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+pub fn init() {
+	let filter = tracing_subscriber::EnvFilter::try_from_env("EDUGAME_LOG")
+		.unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("edugame=info"));
+	let _ = tracing_subscriber::fmt().with_env_filter(filter).try_init();
+
+	let sensitive = std::env::var("EDUGAME_LOG_SENSITIVE").is_ok_and(|v| v == "1" || v == "true");
+	SENSITIVE.get_or_init(|| sensitive);
+}
+
+fn show_sensitive() -> bool {
+	*SENSITIVE.get().unwrap_or(&false)
+}
+
+// alumno.apellido@alumnos.uahurtado.cl -> al***@alumnos.uahurtado.cl
+pub fn redact_email(email: &str) -> String {
+	if show_sensitive() {
+		return email.to_string();
+	}
+	match email.split_once('@') {
+		Some((user, domain)) => format!("{}***@{}", user.chars().take(2).collect::<String>(), domain),
+		None => format!("{}***", email.chars().take(2).collect::<String>()),
+	}
+}
+
+// Notas, promedios y asistencias solo se muestran si se pidio explicitamente
+pub fn redact<T: std::fmt::Debug>(value: &T) -> String {
+	if show_sensitive() {
+		format!("{:?}", value)
+	} else {
+		"[oculto]".to_string()
+	}
+}
+
+// Id de cada peticion, sale en todos los logs de esa peticion y en el header X-Request-Id
+#[derive(Debug, Clone, Copy)]
+pub struct RequestId(pub u64);
+
+impl std::fmt::Display for RequestId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{:06}", self.0)
+	}
+}
+
+fn request_id(request: &Request<'_>) -> RequestId {
+	*request.local_cache(|| RequestId(NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)))
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+	type Error = ();
+
+	async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+		Outcome::Success(request_id(request))
+	}
+}
+
+// Fairing que le pone id a cada peticion y loguea como termino, con la ruta y no la url para no dejar correos en /balance/<username>
+pub struct RequestLogger;
+
+#[rocket::async_trait]
+impl Fairing for RequestLogger {
+	fn info(&self) -> Info {
+		Info { name: "Request logger", kind: Kind::Request | Kind::Response }
+	}
+
+	async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+		request_id(request);
+	}
+
+	async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+		let id = request_id(request);
+		let route = request.route().map(|r| r.uri.to_string()).unwrap_or("-".to_string());
+		tracing::info!(request_id = %id, method = %request.method(), route = %route, status = response.status().code, "peticion terminada");
+		response.set_header(Header::new("X-Request-Id", id.to_string()));
+	}
+}
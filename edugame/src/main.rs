@@ -1,11 +1,37 @@
 use rocket::serde::{Deserialize, Serialize, json::Json};
 use rocket::{get, post, routes, launch, form::Form, response::content::RawHtml, State};
-use reqwest::Client;
 use std::collections::HashMap;
 use std::sync::Arc;
 use rocket::tokio::sync::RwLock;
 use rocket::serde::json::serde_json;
 use rand::Rng;
+use tracing::Instrument;
+use chrono::{DateTime, Utc};
+
+mod catalog;
+use catalog::CourseCatalog;
+mod season;
+use season::{SeasonArchive, SeasonState, Seasons, Standing};
+mod admin;
+use admin::Admin;
+mod rules;
+use rules::{LevelCurve, LevelRules, LevelTier, Metric, RankRewards, RewardPeriod, RuleSet, TeamRules, XpRules};
+mod achievements;
+use achievements::{Badge, Facts};
+mod timeline;
+use timeline::{EventKind, TimelineEvent, TimelineFilter};
+mod teams;
+use teams::{Lobby, TeamStorage, TeamTotals, Teams};
+mod checkin;
+use checkin::Streak;
+mod parser;
+use parser::GradeEntry;
+mod cache;
+use cache::{ScrapeCache, StaleCourse};
+mod logging;
+use logging::{RequestId, RequestLogger, redact_email};
+mod ucampus;
+use ucampus::{CourseData, FailedCourse, GradeSource, ScrapeError, UCampus};
 
 
 /* Estrctura para el logeo, si ocupamos muchas estructuras, originalmente tenia pensado en tener un
@@ -14,17 +40,21 @@ use rand::Rng;
  * el leaderboard y datos de los que jugaron mueren junto con el termino de la ejecion de codigo
  */
 
-#[derive(Debug, Serialize, Deserialize, rocket::form::FromForm)]
+#[derive(Deserialize, rocket::form::FromForm)]
 #[serde(crate = "rocket::serde")]
 struct LoginForm {
 	username: String,
 	password: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ApiResponse {
-	status: i32,
-	u: Option<String>,
+// Debug a mano para que la contraseña no termine nunca en un log, ni aunque alguien haga {:?} del formulario
+impl std::fmt::Debug for LoginForm {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("LoginForm")
+			.field("username", &redact_email(&self.username))
+			.field("password", &"[oculto]")
+			.finish()
+	}
 }
 
 
@@ -42,9 +72,100 @@ struct Student {
 	penalty: u8,
 	bonus: u8,
 	coins: u32,
+	/*
+	 * El detalle de cada curso (notas, promedios, castigos) no sale en /balance, que no pide contraseña.
+	 * Solo lo ve el estudiante en la respuesta de /scrape, que se agrega a mano en scrape_handler
+	 */
+	#[serde(default, skip_serializing)]
+	courses: HashMap<String, CourseProgress>,	// Lo ultimo que se vio de cada curso y la experiencia que ya se entrego por el
+	#[serde(default)]
+	decimal_bonus: f32,							// Decimas compradas en la tienda, se suman al promedio en cada actualizacion
+	#[serde(default)]
+	achievements: HashMap<String, DateTime<Utc>>,	// Logros desbloqueados, id -> cuando se desbloqueo
+	#[serde(default, skip_serializing)]
+	active_penalties: Vec<ActivePenalty>,		// Castigos vigentes de todos los cursos, el mas severo primero
+	#[serde(default)]
+	title: Option<String>,						// Nombre del titulo del nivel actual
+	#[serde(default)]
+	level_rewards: HashMap<String, DateTime<Utc>>,	// Titulos cuyo premio ya se pago, id -> cuando
+	#[serde(default)]
+	streak: Streak,								// Racha de check-in diario
+	#[serde(default)]
+	team_goals: HashMap<String, DateTime<Utc>>,	// Metas de equipo ya cobradas esta temporada, id -> cuando
+	#[serde(default, skip_serializing)]
+	timeline: Vec<TimelineEvent>,				// Historial de experiencia y monedas, sale por /students/<username>/timeline y no en /balance
+}
+
+// Lo que se guarda de cada curso para que al volver a scrapear solo se premie lo nuevo
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "rocket::serde")]
+struct CourseProgress {
+	mean: f32,
+	attendance: f32,
+	grades: HashMap<String, f32>,	// Evaluaciones con nota, nombre -> nota
+	#[serde(default)]
+	sequence: Vec<f32>,				// Las mismas notas pero en el orden de ucampus, para ver si van subiendo
+	awarded_exp: u32,				// La mas alta que se ha entregado por el curso, sin contar castigos
+	#[serde(default)]
+	penalty_exp: u32,				// XP descontada por los castigos activos, se devuelve cuando se levantan
+	bonus: u8,
+	penalty: u8,
+	// Desglose para que el estudiante vea de donde salio lo que gano en este curso
+	#[serde(default)]
+	base_exp: u32,					// Lo que dio la formula base con el promedio y la asistencia
+	#[serde(default)]
+	tiers: Vec<TierHit>,			// Tramos de bonus alcanzados
+	#[serde(default)]
+	penalties: Vec<ActivePenalty>,	// Castigos que tiene el curso ahora
+	#[serde(default)]
+	coins_minted: u32,				// Monedas que se han entregado por este curso, sumando todos los scrapeos
+	#[serde(default)]
+	best_mean: f32,					// El mejor promedio que ha tenido el curso, la mejora se paga solo sobre esto
+	#[serde(default)]
+	best_attendance: f32,
+	#[serde(default)]
+	improvement_exp: u32,			// XP entregada por mejoras en este curso
+}
+
+// Castigo por tramo que tiene un curso, se levanta solo cuando el curso sale del tramo
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+struct ActivePenalty {
+	course: String,
+	metric: Metric,
+	at_most: f32,
+	severity: u8,
+	exp: u32,
+	since: DateTime<Utc>,	// Desde cuando tiene esta severidad
+}
+
+// Un tramo de config/rules.json que el curso alcanzo
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+struct TierHit {
+	metric: Metric,
+	min: f32,
+	exp: u32,
+	bonus: u8,
+}
+
+#[derive(Debug, Clone)]
+// Sistema de niveles, con la curva y los titulos de config/rules.json
+struct LevelSystem {
+	thresholds: Vec<u32>,	// Experiencia minima de cada nivel, thresholds[0] es el nivel 1 y siempre es 0
+	tiers: Vec<LevelTier>,
+}
+
+// Cuanto le falta a un estudiante para el siguiente nivel
+#[derive(Debug, Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+struct LevelProgress {
+	level: u16,
+	exp: u32,
+	level_exp: u32,					// Experiencia con la que se llego al nivel actual
+	next_level_exp: Option<u32>,	// None en el nivel maximo
+	percent: f32,
 }
-#[derive(Debug)]
-struct LevelSystem;
 
 /*impl LevelSystem {
  *    fn level_for_exp(&self, exp: u32) -> u16 {
@@ -66,12 +187,70 @@ struct LevelSystem;
 
 // Implementación del sistema de niveles, el viejo era un crimen contra la humanidad y empezamos a utlizar u16
 impl LevelSystem {
-	fn level_for_exp(&self, exp: u32) -> u16 {
-		if exp < 100 {
-			1
-		} else {
-			1 + (((exp as f64 / 2.0).sqrt().floor()) as u16 + 1) as u16 // Sistema para subir de nivel en base a la experiencia, mientras mas experiencia mas alto el nivel
+	// Arma la tabla de cada curva hasta max_level, asi subir de nivel y la inversa salen de la misma tabla
+	fn new(rules: &LevelRules) -> Self {
+		let mut thresholds = vec![0u32];
+		for level in 2..=rules.max_level as u64 {
+			let exp: u64 = match &rules.curve {
+				// 1 + floor(sqrt(exp / 2)) + 1 desde los 100 XP, el minimo para llegar a nivel n es 2 * (n - 2)^2
+				LevelCurve::Sqrt => (2 * (level - 2).pow(2)).max(100),
+				// Como exp_table de local_code2.rs, 1^3 + 2^3 + ... + (n - 1)^3
+				LevelCurve::Cubic => (1..level).map(|k| k.pow(3)).sum(),
+				LevelCurve::Linear { exp_per_level } => (level - 1) * *exp_per_level as u64,
+				LevelCurve::Table { exp } => match exp.get(level as usize - 2) {
+					Some(exp) => *exp as u64,
+					None => break, // La tabla se acabo antes de max_level
+				},
+			};
+			thresholds.push(exp.min(u32::MAX as u64) as u32);
 		}
+		LevelSystem { thresholds, tiers: rules.tiers.clone() }
+	}
+
+	fn max_level(&self) -> u16 {
+		self.thresholds.len() as u16
+	}
+
+	// Sistema para subir de nivel en base a la experiencia, el nivel mas alto cuya experiencia minima ya se tiene
+	fn level_for_exp(&self, exp: u32) -> u16 {
+		self.thresholds.partition_point(|&threshold| threshold <= exp) as u16
+	}
+
+	// La inversa, la experiencia necesaria para llegar al nivel (None si pasa del maximo)
+	fn exp_for_level(&self, level: u16) -> Option<u32> {
+		self.thresholds.get(level.max(1) as usize - 1).copied()
+	}
+
+	fn progress(&self, exp: u32) -> LevelProgress {
+		let level = self.level_for_exp(exp);
+		let level_exp = self.exp_for_level(level).unwrap_or(0);
+		let next_level_exp = self.exp_for_level(level + 1);
+		let percent = match next_level_exp {
+			Some(next) => (exp - level_exp) as f32 / (next - level_exp) as f32 * 100.0,
+			None => 100.0,
+		};
+		LevelProgress { level, exp, level_exp, next_level_exp, percent }
+	}
+
+	// El titulo del nivel, el ultimo cuyo min_level ya se alcanzo
+	fn tier_for(&self, level: u16) -> Option<&LevelTier> {
+		self.tiers.iter().rev().find(|tier| level >= tier.min_level)
+	}
+
+	// El titulo que desbloquea el juego, si ningun titulo lo tiene el juego esta abierto para todos
+	fn unlocked_by(&self, game: &str) -> Option<&LevelTier> {
+		self.tiers.iter().find(|tier| tier.unlocks.iter().any(|g| g == game))
+	}
+}
+
+// Error 403 si el estudiante todavia no tiene el titulo que abre el juego
+fn check_game_unlocked(student: &Student, game: &str, level_system: &LevelSystem) -> Result<(), rocket::response::status::Custom<String>> {
+	match level_system.unlocked_by(game) {
+		Some(tier) if student.level < tier.min_level => Err(rocket::response::status::Custom(
+			rocket::http::Status::Forbidden,
+			format!("Este juego se desbloquea con el titulo {} (nivel {})", tier.name, tier.min_level)
+		)),
+		_ => Ok(()),
 	}
 }
 
@@ -90,6 +269,34 @@ struct CoinFlipForm {
 	choice: String,
 }
 
+// Check-in diario, solo se necesita quien marca
+#[derive(Debug, rocket::form::FromForm)]
+struct CheckInForm {
+	username: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct CheckInResult {
+	message: String,
+	streak: u32,
+	longest: u32,
+	freezes_used: u32,
+	freezes_left: u32,
+	coins: u32,
+	exp: u32,
+	new_balance: u32,
+	level: u16,
+	new_achievements: Vec<Badge>,
+}
+
+// Formulario de equipos, team es el nombre al crear/asignar y el id al unirse, al salir no se usa
+#[derive(Debug, rocket::form::FromForm)]
+struct TeamForm {
+	username: String,
+	team: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 struct CoinFlipResult {
@@ -98,6 +305,7 @@ struct CoinFlipResult {
 	coins_won: u32,
 	coins_lost: u32,
 	new_balance: u32,
+	new_achievements: Vec<Badge>,
 }
 
  /*
@@ -120,6 +328,7 @@ struct SlotsResult {
 	payout: u32,			//Nadie nunca debería tener balance,monto apostado, pago negativo
 	amount_wagered: u32,
 	win_type: Option<String>, // Dos iguales, Tres iguales
+	new_achievements: Vec<Badge>,
 
 }
 
@@ -153,6 +362,8 @@ struct PurchaseResult {
 	new_balance: u32,
 	item_received: String,
 	quantity: u32,
+	discount: u8,			// Porcentaje que se descontó por el titulo
+	new_achievements: Vec<Badge>,
 }
 
 
@@ -166,501 +377,473 @@ type StudentStorage = Arc<RwLock<HashMap<String, Student>>>;
 
 impl Student {
 
-	// Calcular la media de cada curso, entrará como vector y saldra en float, esto se hará para cada una de las materias, algebra, calculo, habilidades y electivo
-	fn calculate_course_mean(grades: &Vec<String>) -> f32 {
+	/*
+	 * Calcular la media de cada curso, entrará como vector y saldra en float, esto se hará para cada una de las materias del catalogo.
+	 * Si todas las notas que ya estan puestas traen ponderacion se usa el promedio ponderado (lo que realmente lleva el alumno),
+	 * se divide por la suma de las ponderaciones ya evaluadas y no por 100 para que no baje solo porque faltan notas.
+	 * Si a alguna le falta la ponderacion volvemos al promedio simple de antes
+	 */
+	fn calculate_course_mean(grades: &[GradeEntry]) -> f32 {
+		let graded: Vec<&GradeEntry> = grades.iter().filter(|entry| entry.grade.is_some()).collect();
+
+		if !graded.is_empty() && graded.iter().all(|entry| entry.weight.is_some_and(|w| w > 0.0)) {
+			let mut weighted_total = 0.0;
+			let mut weight_total = 0.0;
+			for entry in &graded {
+				let weight = entry.weight.unwrap_or(0.0);
+				weighted_total += entry.grade.unwrap_or(0.0) * weight;
+				weight_total += weight;
+			}
+			return weighted_total / weight_total;
+		}
+
 		let mut total = 0.0;
 		let mut count = 0;
 		
-		for grade in grades {
-			if let Ok(grade_val) = grade.parse::<f32>() {
-				total += grade_val;
-				count += 1;
-			}
+		for grade_val in graded.iter().filter_map(|entry| entry.grade) {
+			total += grade_val;
+			count += 1;
 		}
 		
 		if count > 0 { 
-			return total / count as f32 
+			total / count as f32 
 		} else { 
-			return 0.0
+			0.0
 		}
 	}
 	
 
-	// Aplicar la gamificación del curso, se le pasa el promedio de curso, la asistencia, y se le aplica el sistema de niveles
-	fn apply_course_gamification(&mut self, course_mean: f32, course_attendance: f32, level_system: &LevelSystem) {
-		let base_exp = ((course_mean - 1.0) / 6.0) * 50.0 + (course_attendance / 100.0) * 50.0; // En base al promedio del curso, a su asistencia, se saca un puntaje, nos inventamos esta formula que calcula la experiencia 
-		let mut gained_exp = base_exp.max(0.0).round() as u32; // Experiencia extra que se dará de bonificacion al usuario si, 
-		
-		if course_mean >= 5.5 { // Tiene promedio mayor igual a 5.5
-			gained_exp += 100;
-			self.bonus += 1;
-		}
-		if course_mean >= 6.0 { // Tiene promedio mayor igual a 6.0
-			gained_exp += 200;
-			self.bonus += 1;
-		}
-		if course_mean >= 6.5 { // Tiene promedio mayor igual a 6.5
-			gained_exp += 350;
-			self.bonus += 1;
-		}
-		
-		// Todas se aplican mas de una vez, osea el que tiene promedio 7.0 ganara los primeros 100 de xp, luego otros 200 y otros 350
-
-
-		if course_attendance >= 80.0 { // Tiene asistencia mayor igual a 80%
-			gained_exp += 225;
-			self.bonus += 1;
-		}
-		if course_attendance >= 85.0 { // Tiene asistencia mayor igual a 85%
-			
-			gained_exp += 100;
-			self.bonus += 1;
-		}
-		if course_attendance >= 90.0 { // Tiene asistencia mayor igual a 90%
-			gained_exp += 225;
-			self.bonus += 2;
-		}
-		
-		// Penalizaciones, si el promedio es menor a 4.5 o la asistencia es menor a 65% se le resta experiencia y se le aplica una penalizacion
-
-		if course_mean < 4.5 {
-			gained_exp = gained_exp.saturating_sub(125);
-			self.penalty += 1;
-		}
-		if course_attendance < 65.0 {
-			gained_exp = gained_exp.saturating_sub(125);
-			self.penalty += 1;
+	// Un estudiante recien llegado, la unica base que se da son las 100 monedas
+	fn new(name: String) -> Self {
+		Student {
+			name,
+			assist: 0,
+			grades: 0,
+			mean: 0.0,
+			exp: 0,
+			level: 1,
+			penalty: 0,
+			bonus: 0,
+			coins: 100,
+			courses: HashMap::new(),
+			decimal_bonus: 0.0,
+			achievements: HashMap::new(),
+			active_penalties: vec![],
+			title: None,
+			level_rewards: HashMap::new(),
+			streak: Streak::default(),
+			team_goals: HashMap::new(),
+			timeline: vec![],
 		}
-		
-		self.exp += gained_exp; //Se le suma la experiencia
-		self.level = level_system.level_for_exp(self.exp); //Se le calcula el nivel
-
-		let coin_bonus = (self.level as u32 * 2) + (gained_exp / 10);
-		self.coins += (coin_bonus * self.exp) / 100;
-		// Sistema para bonificacion de monedas, mientras mas XP mas monedas
 	}
-	
-
-	// Sistema de bonificacion aplicado
-	fn apply_full_gamification(&mut self, 
-		electivo_grades: &Vec<String>, //Por cada nota de cada materia elegida se calculara apply_course_gamification
-		electivo_attendance: f32,
-		habilidades_grades: &Vec<String>, 
-		habilidades_attendance: f32,
-		algebralineal_grades: &Vec<String>, 
-		algebralineal_attendance: f32,
-		calculointegral_grades: &Vec<String>, 
-		calculointegral_attendance: f32,
-		level_system: &LevelSystem) {
-		
-		self.exp = 0;
-		self.level = 1;
-		self.penalty = 0;
-		self.bonus = 0;
-		self.coins = 100;	//La unica base que se da son las 100 monedas
-		
-		let electivo_mean = Self::calculate_course_mean(electivo_grades);				//Todo es muy rebundnte pero no se me ocurrio otra manera de hacerlo, aquí se calcula la media de las notas de los vectores que almacenan las notas
-		let habilidades_mean = Self::calculate_course_mean(habilidades_grades);
-		let algebralineal_mean = Self::calculate_course_mean(algebralineal_grades);
-		let calculointegral_mean = Self::calculate_course_mean(calculointegral_grades);
-		
-		self.apply_course_gamification(electivo_mean, electivo_attendance, level_system);		
-		self.apply_course_gamification(habilidades_mean, habilidades_attendance, level_system);
-		self.apply_course_gamification(algebralineal_mean, algebralineal_attendance, level_system);
-		self.apply_course_gamification(calculointegral_mean, calculointegral_attendance, level_system);
-		
-		let total_grades = electivo_mean + habilidades_mean + algebralineal_mean + calculointegral_mean; 
-		self.mean = total_grades / 4.0;
-		
 
-		let mut total_individual_grades = 0.0;
-		
-		// Se suman todas las notas de cada materia, se multiplica por 10 para que sea un valor mas alto y se pueda usar en el sistema de niveles
-		for grades_vec in [electivo_grades, habilidades_grades, algebralineal_grades, calculointegral_grades].iter() {
-			for grade in grades_vec.iter() {
-				if let Ok(grade_val) = grade.parse::<f32>() {
-					total_individual_grades += grade_val;
-				}
-			}
+	// Deja un evento en el historial con como quedo el estudiante despues del cambio
+	fn record(&mut self, kind: EventKind, description: String, exp_delta: i64, coins_delta: i64) {
+		self.timeline.push(TimelineEvent {
+			at: Utc::now(),
+			kind,
+			description,
+			exp_delta,
+			coins_delta,
+			exp: self.exp,
+			coins: self.coins,
+			level: self.level,
+		});
+		if self.timeline.len() > timeline::MAX_EVENTS {
+			let excess = self.timeline.len() - timeline::MAX_EVENTS;
+			self.timeline.drain(..excess);
 		}
-		
-		self.grades = (total_individual_grades * 10.0) as u16;
-		// Se multiplica por 10 para que sea un enetro
-
-		self.assist = ((electivo_attendance + habilidades_attendance + algebralineal_attendance + calculointegral_attendance) / 4.0) as u8;
-		//Guardamos la media de la asistencia entre los 4 cursos
 	}
-}
-
-async fn scrape_ucampus(username: String, password: String) -> Result<Student, Box<dyn std::error::Error + Send + Sync>> {
-
-	/*
-	 * Armado de el cliente para las peticiones web de ucampus PD: Todo lo saque y probe con postman interceptor, despues con flask en python y ahí lo traduje a oxido
-	 * donde su equivalencia es reqwest, tambien usamos tokio y serde para las respuestas en json
- 	 */
-
-	let client = Client::builder()
-		.cookie_store(true)
-		.build()?;
-
-	let cookie_monster = client
-		.get("https://ucampus.uahurtado.cl/")
-		.send()
-		.await?;
 
 	/*
-	 * Extraigo las cookies que en las otras peticiones almacenan la sesion
- 	 */
-
-	let cookies = cookie_monster.cookies().collect::<Vec<_>>();
-	let sess_cookie = cookies
-		.iter()
-		.find(|c| c.name() == "_ucampus")
-		.ok_or("Session cookie not found")?; //No deberia pasar NUNCA a no ser que pase un problema
-
-	let mut login_data = HashMap::new();
-	login_data.insert("servicio", "ucampus");
-	login_data.insert("debug", "0");
-	login_data.insert("_sess", sess_cookie.value());
-	login_data.insert("_LB", "uah02-int");
-	login_data.insert("lang", "es");
-	login_data.insert("username", &username);
-	login_data.insert("password", &password);
-	login_data.insert("recordar", "1");
-
-	let login_response = client
-		.post("https://ucampus.uahurtado.cl/auth/api")
-		.header("User-Agent", "GamificationEngineRuntime/7.44.1") //No funciona y llega el correo a quien lo usa que ingresaron de un dispositivo unknown
-		.form(&login_data)
-		.send()
-		.await?;
-
-	let login_json: ApiResponse = login_response.json().await?;
-
-	if login_json.status != 200 {
-		return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Error. Verifica tu usuario y contraseña.")));
-	}
-
-	//Si login_json.u tiene algo entonces se guarda en main_url, la no existencia de valores nulos nos deja con el uso de Some
-	// el uso de some lo utilizo para varias veces luego cuando tenga que extraer las notas de las materias
-	if let Some(main_url) = login_json.u {
-		client.get(&main_url).send().await?;
+	 * Deja en el historial un cambio de experiencia que ya se aplico y recalcula el nivel. El nivel se calcula antes de
+	 * guardar el evento para que quede con el nivel nuevo (el evento es como quedo el estudiante despues), y la subida
+	 * de nivel y los titulos se guardan despues, como consecuencia del evento
+	 */
+	fn record_exp_change(&mut self, kind: EventKind, description: String, exp_delta: i64, coins_delta: i64, level_system: &LevelSystem) {
+		let previous = self.level;
+		self.level = level_system.level_for_exp(self.exp);
+		self.record(kind, description, exp_delta, coins_delta);
+		self.level_up(previous, level_system);
 	}
 
 	/*
-	 *  Seccion - Inicio
-	 *       Electivo especialidad
+	 * Si el nivel subio queda en el historial y se pagan los titulos alcanzados.
+	 * Cada titulo paga una sola vez (level_rewards), aunque el nivel baje y vuelva a subir
 	 */
-
-	let mut grades1_response = client
-		.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0168/1/notas/alumno")
-		.send()
-		.await?;
-	let mut grades1_text = grades1_response.text().await?;
-	if grades1_text.contains("No tienes permisos para ver esta") { 
-		//En caso de que no esten en la seccion 1 prueba a ver en la seccion 2, el formato de la url es CSIXXXX/[seccion]/notas/alumno
-		grades1_response = client.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0168/2/notas/alumno")
-		.send()
-		.await?;
-		grades1_text = grades1_response.text().await?;
+	fn level_up(&mut self, previous: u16, level_system: &LevelSystem) {
+		if self.level > previous {
+			self.record(EventKind::LevelUp, format!("Subiste de nivel {} a {}", previous, self.level), 0, 0);
+			let level = self.level;
+			for tier in level_system.tiers.iter().filter(|tier| tier.min_level <= level) {
+				if self.level_rewards.contains_key(&tier.id) {
+					continue;
+				}
+				self.level_rewards.insert(tier.id.clone(), Utc::now());
+				self.coins += tier.coins;
+				let reward = if tier.coins > 0 { format!(" (+{} monedas)", tier.coins) } else { String::new() };
+				self.record(EventKind::LevelUp, format!("Nuevo titulo: {}{}", tier.name, reward), 0, tier.coins as i64);
+			}
+		}
+		self.title = level_system.tier_for(self.level).map(|tier| tier.name.clone());
 	}
 
-
-	let mut attendance1_response = client
-		.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0168/1/asistencias2/")
-		.send()
-		.await?;
-	let mut attendance1_text = attendance1_response.text().await?;
-	if attendance1_text.contains("No tienes permisos para ver esta") {
-		attendance1_response = client.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0168/2/asistencias2/")
-		.send()
-		.await?;
-		attendance1_text = attendance1_response.text().await?;
+	fn achievement_facts(&self, slots_jackpot: bool) -> Facts {
+		Facts {
+			level: self.level,
+			best_attendance: self.courses.values().map(|c| c.attendance).fold(0.0, f32::max),
+			best_grade: self.courses.values().flat_map(|c| c.grades.values().copied()).fold(0.0, f32::max),
+			improving_streak: self.courses.values().map(|c| achievements::longest_improving_run(&c.sequence)).max().unwrap_or(0),
+			slots_jackpot,
+		}
 	}
 
-	let mut base_1 = 1;
-	if grades1_text.contains("Examen") {
-		base_1 = base_1 + 1;
+	// Se llama despues de cada scrapeo, juego y compra, devuelve los logros que se acaban de desbloquear
+	fn check_achievements(&mut self, slots_jackpot: bool) -> Vec<Badge> {
+		let facts = self.achievement_facts(slots_jackpot);
+		achievements::evaluate(&mut self.achievements, &facts, Utc::now())
 	}
 
 	/*
-	 *  Cuando alguien tiene que dar examen aparece un formulario vacio donde antes estaba la primera nota, esto lo arregla
+	 * Aplicar la gamificación del curso, se le pasa el curso scrapeado (promedio ponderado cuando se puede y asistencia) y se le aplica el sistema de niveles.
+	 * Si el curso no cambio desde el ultimo scrapeo no se hace nada, y si cambio solo se entrega la experiencia que falta
+	 * respecto a la que ya se le dio por ese curso, asi volver a loguearse no regala ni quita nada
 	 */
+	fn apply_course_gamification(&mut self, course: &CourseData, rules: &RuleSet, level_system: &LevelSystem) {
+		let course_mean = Self::calculate_course_mean(&course.grades);
+		let course_attendance = course.attendance;
+		let graded: HashMap<String, f32> = course.grades.iter()
+			.filter_map(|entry| entry.grade.map(|grade| (entry.name.clone(), grade)))
+			.collect();
+		let sequence: Vec<f32> = course.grades.iter().filter_map(|entry| entry.grade).collect();
+
+		let previous = self.courses.get(&course.code).cloned();
+		if let Some(prev) = &previous && prev.grades == graded && prev.attendance == course_attendance {
+			return; // Nada nuevo en este curso
+		}
 
-	let mut electivo_grades = vec![];
-
-	if let Some(grade) = extract_nth_between(&grades1_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_1 as usize) {
-		electivo_grades.push(grade);
-	}
-	if let Some(grade) = extract_nth_between(&grades1_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_1 + 1) {
-		electivo_grades.push(grade); // En caso de no existir no queda un vector con un valor vacio y queda solo con la longitud de los mismos valores que encontro
-	}
-
+		let mut bonus: u8 = 0;
+		let mut penalty: u8 = 0;
+
+		let base_exp = ((course_mean - 1.0) / 6.0) * rules.base_grade_exp + (course_attendance / 100.0) * rules.base_attendance_exp; // En base al promedio del curso, a su asistencia, se saca un puntaje, nos inventamos esta formula que calcula la experiencia 
+		let base_exp = base_exp.max(0.0).round() as u32;
+		let mut gained_exp = base_exp; // Experiencia extra que se dará de bonificacion al usuario si, 
+		let mut tiers = vec![];
+		let mut penalties = vec![];
+
+		// Tramos de config/rules.json, se aplican todos los que se cumplan, osea el que tiene promedio 7.0 gana los de 5.5, 6.0 y 6.5
+		let grade_hits = rules.grade_tiers.iter().filter(|tier| course_mean >= tier.min).map(|tier| (Metric::Grade, tier));
+		let attendance_hits = rules.attendance_tiers.iter().filter(|tier| course_attendance >= tier.min).map(|tier| (Metric::Attendance, tier));
+		for (metric, tier) in grade_hits.chain(attendance_hits) {
+			gained_exp += tier.exp;
+			bonus = bonus.saturating_add(tier.bonus);
+			tiers.push(TierHit { metric, min: tier.min, exp: tier.exp, bonus: tier.bonus });
+		}
 
-	let electivo_attendance = extract_attendance(&attendance1_text, "<th>Asistencia", "%</h1>")
-		.unwrap_or("0".to_string()).replace(">", "");
+		/*
+		 * Castigos por tramos, de cada metrica se toma el tramo mas severo en que cae el curso y la severidad va al contador de castigos.
+		 * La experiencia del castigo va aparte de awarded_exp (penalty_exp), si no un curso ya premiado podia caer en un tramo
+		 * sin perder nada. Al caer se descuenta, al cambiar de severidad se ajusta la diferencia y al salir del tramo se devuelve
+		 */
+		let now = Utc::now();
+		let mut penalty_exp: u32 = 0;
+		let mut penalty_events = vec![];
+		for (metric, value) in [(Metric::Grade, course_mean), (Metric::Attendance, course_attendance)] {
+			let before = previous.as_ref().and_then(|prev| prev.penalties.iter().find(|p| p.metric == metric)).cloned();
+			match rules.penalty_for(metric, value) {
+				Some(rule) => {
+					penalty_exp += rule.exp;
+					penalty = penalty.saturating_add(rule.severity);
+					let since = match &before {
+						Some(active) if active.severity == rule.severity => active.since, // Sigue igual, se mantiene desde cuando
+						_ => {
+							let delta = before.as_ref().map_or(0, |active| active.exp as i64) - rule.exp as i64;
+							penalty_events.push((format!("{}: castigo de severidad {} por {} (-{} XP del curso)", course.code, rule.severity, metric.label(), rule.exp), delta));
+							now
+						},
+					};
+					penalties.push(ActivePenalty {
+						course: course.code.clone(),
+						metric,
+						at_most: rule.at_most,
+						severity: rule.severity,
+						exp: rule.exp,
+						since,
+					});
+				},
+				None => if let Some(active) = before {
+					penalty_events.push((format!("{}: se levanta el castigo de severidad {} por {} (+{} XP de vuelta)", course.code, active.severity, metric.label(), active.exp), active.exp as i64));
+				},
+			}
+		}
 
-	/*
-	 *  Lo que explique arriba ahora 3 veces mas
-	 */
+		// Solo se suma lo que no se habia entregado antes por este curso, si el curso bajo no se quita lo ya ganado (de eso se encargan los castigos)
+		let already_awarded = previous.as_ref().map(|prev| prev.awarded_exp).unwrap_or(0);
+		let mut coins_minted = previous.as_ref().map(|prev| prev.coins_minted).unwrap_or(0);
+		let new_exp = gained_exp.saturating_sub(already_awarded);
+
+		// Del castigo se descuenta solo lo que falta descontar, y si bajo o se levanto se devuelve la diferencia
+		let penalty_before = previous.as_ref().map(|prev| prev.penalty_exp).unwrap_or(0);
+		let deducted = penalty_exp.saturating_sub(penalty_before).min(self.exp + new_exp);
+		let refunded = penalty_before.saturating_sub(penalty_exp);
+
+		if new_exp > 0 {
+			self.exp += new_exp; //Se le suma la experiencia
+			let level = level_system.level_for_exp(self.exp); //Se le calcula el nivel
+			let coin_exp = new_exp.saturating_sub(deducted); // Las monedas salen de lo que queda despues del castigo
+
+			/*
+			 * Sistema para bonificacion de monedas, mientras mas XP mas monedas. Antes se multiplicaba por toda la experiencia
+			 * del estudiante porque se reseteaba en cada login, ahora que se paga solo lo nuevo se multiplica por lo nuevo,
+			 * si no cada clase asistida acuñaba monedas por todo el saldo
+			 */
+			let coin_bonus = (level as u32 * rules.coins.level_multiplier) + (coin_exp / rules.coins.exp_divisor);
+			let minted = (coin_bonus * coin_exp) / rules.coins.total_exp_divisor;
+			self.coins += minted;
+			coins_minted += minted;
+
+			self.record_exp_change(EventKind::XpGrant, format!("{}: notas y asistencia actualizadas", course.code), new_exp as i64, minted as i64, level_system);
+		}
+		self.exp = self.exp - deducted + refunded;
+		let penalty_exp = penalty_before + deducted - refunded; // Lo que de verdad se desconto, si la experiencia no alcanzaba queda corto
+		for (description, exp_delta) in penalty_events {
+			self.record_exp_change(EventKind::Penalty, description, exp_delta, 0, level_system); // Con el castigo el nivel tambien puede bajar
+		}
 
-	/*
-	 *  Seccion - Fin
-	 *       Electivo especialidad
-	 */
+		/*
+		 * Mejora respecto al scrapeo anterior, se compara contra lo mejor que ha tenido el curso y no contra lo ultimo,
+		 * asi bajar y volver a subir al mismo promedio no da nada. El primer scrapeo de un curso solo deja la marca
+		 */
+		let best_mean = previous.as_ref().map(|prev| prev.best_mean.max(prev.mean)).unwrap_or(course_mean);
+		let best_attendance = previous.as_ref().map(|prev| prev.best_attendance.max(prev.attendance)).unwrap_or(course_attendance);
+		let mean_gain = (course_mean - best_mean).max(0.0);
+		let attendance_gain = (course_attendance - best_attendance).max(0.0);
+		let improvement = (mean_gain * rules.improvement.exp_per_grade_point + attendance_gain * rules.improvement.exp_per_attendance_point).round() as u32;
+		let improvement_exp = previous.as_ref().map(|prev| prev.improvement_exp).unwrap_or(0) + improvement;
+
+		if improvement > 0 {
+			self.exp += improvement;
+			self.record_exp_change(EventKind::XpGrant, format!("{}: mejora sobre tu mejor {}", course.code, match (mean_gain > 0.0, attendance_gain > 0.0) {
+				(true, true) => "promedio y asistencia",
+				(true, false) => "promedio",
+				_ => "asistencia",
+			}), improvement as i64, 0, level_system);
+		}
 
-	/*
-	 *  Seccion - Inicio
-	 *       Habilidades III
-	 */
-	let mut grades2_response = client
-		.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0169/1/notas/alumno")
-		.send()
-		.await?;
-	let mut grades2_text = grades2_response.text().await?;
-	if grades2_text.contains("No tienes permisos para ver esta") {
-		grades2_response = client.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0169/2/notas/alumno")
-		.send()
-		.await?;
-		grades2_text = grades2_response.text().await?;
-	}
-
-	let mut attendance2_response = client
-		.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0169/1/asistencias2/")
-		.send()
-		.await?;
-	let mut attendance2_text = attendance2_response.text().await?;
-	if attendance2_text.contains("No tienes permisos para ver esta") {
-		attendance2_response = client.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0169/2/asistencias2/")
-		.send()
-		.await?;
-		attendance2_text = attendance2_response.text().await?;
+		self.courses.insert(course.code.clone(), CourseProgress {
+			mean: course_mean,
+			attendance: course_attendance,
+			grades: graded,
+			sequence,
+			awarded_exp: already_awarded.max(gained_exp),
+			penalty_exp,
+			bonus,
+			penalty,
+			base_exp,
+			tiers,
+			penalties,
+			coins_minted,
+			best_mean: best_mean.max(course_mean),
+			best_attendance: best_attendance.max(course_attendance),
+			improvement_exp,
+		});
 	}
 	
-	let mut base_2 = 1;
-	if grades2_text.contains("Examen") {
-		base_2 = base_2 + 1;
-	}
-
-
-	let mut habilidades_grades = vec![];
-
-	if let Some(grade) = extract_nth_between(&grades2_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_2 as usize) {
-		habilidades_grades.push(grade);
-	}
-	if let Some(grade) = extract_nth_between(&grades2_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_2 + 1) {
-		habilidades_grades.push(grade);
-	}
-	if let Some(grade) = extract_nth_between(&grades2_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_2 + 2) {
-		habilidades_grades.push(grade);
-	}
-	if let Some(grade) = extract_nth_between(&grades2_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_2 + 3) {
-		habilidades_grades.push(grade);
-	}
-	if let Some(grade) = extract_nth_between(&grades2_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_2 + 4) {
-		habilidades_grades.push(grade);
-	}
-
-	let habilidades_attendance = extract_attendance(&attendance2_text, "<th>Asistencia", "%</h1>")
-		.unwrap_or("0".to_string()).replace(">", "");
-
-	/*
-	 *  Seccion - Fin
-	 *       Habilidades III
-	 */
 
 	/*
-	 *  Seccion - Inicio
-	 *       Algebra lineal
+	 * Sistema de bonificacion aplicado, por cada curso scrapeado se calcula apply_course_gamification.
+	 * Ya no se resetea nada, las monedas de los juegos, lo comprado en la tienda y la experiencia ganada se mantienen.
+	 * Promedio, asistencia, bonus y castigos se recalculan con todos los cursos que tiene guardados
 	 */
+	fn apply_full_gamification(&mut self, courses: &[CourseData], rules: &XpRules, level_system: &LevelSystem) {
+		for course in courses {
+			self.apply_course_gamification(course, rules.for_course(&course.code), level_system);
+		}
 
-	let mut grades3_response = client
-		.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0165/1/notas/alumno")
-		.send()
-		.await?;
-	let mut grades3_text = grades3_response.text().await?;
-	if grades3_text.contains("No tienes permisos para ver esta") {
-		grades3_response = client.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0165/2/notas/alumno")
-		.send()
-		.await?;
-		grades3_text = grades3_response.text().await?;
+		self.bonus = self.courses.values().fold(0u8, |acc, c| acc.saturating_add(c.bonus));
+		self.penalty = self.courses.values().fold(0u8, |acc, c| acc.saturating_add(c.penalty));
+		self.active_penalties = self.courses.values().flat_map(|c| c.penalties.iter().cloned()).collect();
+		self.active_penalties.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.course.cmp(&b.course)));
 
-	}
-	let mut attendance3_response = client
-		.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0165/1/asistencias2/")
-		.send()
-		.await?;
-	let mut attendance3_text = attendance3_response.text().await?;
-	if attendance3_text.contains("No tienes permisos para ver esta") {
-		attendance3_response = client.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0165/2/asistencias2/")
-		.send()
-		.await?;
-		attendance3_text = attendance3_response.text().await?;
-	}
+		// Sin cursos no hay nada que promediar, se queda todo en 0
+		let course_count = self.courses.len().max(1) as f32;
+		let total_means: f32 = self.courses.values().map(|c| c.mean).sum();
+		let total_attendance: f32 = self.courses.values().map(|c| c.attendance).sum();
+		let total_individual_grades: f32 = self.courses.values().flat_map(|c| c.grades.values()).sum();
 
-	let mut base_3 = 1;
-	if grades3_text.contains("Examen") {
-		base_3 = base_3 + 1;
-	}
+		// Las decimas compradas en la tienda se vuelven a sumar para que no se pierdan al actualizar
+		self.mean = total_means / course_count + self.decimal_bonus;
 
-	let mut algebralineal_grades = vec![];
+		self.grades = ((total_individual_grades + self.decimal_bonus) * 10.0) as u16;
+		// Se suman todas las notas de cada materia, se multiplica por 10 para que sea un enetro
 
-	if let Some(grade) = extract_nth_between(&grades3_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_3) {
-		algebralineal_grades.push(grade);
-	}
-	if let Some(grade) = extract_nth_between(&grades3_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_3 + 1) {
-		algebralineal_grades.push(grade);
+		self.assist = (total_attendance / course_count) as u8;
+		//Guardamos la media de la asistencia entre todos los cursos
 	}
-	if let Some(grade) = extract_nth_between(&grades3_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_3 + 2) {
-		algebralineal_grades.push(grade);
-	}
-
-
-	let algebralineal_attendance = extract_attendance(&attendance3_text, "<th>Asistencia", "%</h1>")
-		.unwrap_or("0".to_string()).replace(">", "");
+}
 
-	/*
-	 *  Seccion - Fin
-	 *       Algebra lineal
-	 */
+/*
+ * Premio por puesto (calcular_bonus_puesto de local_code.rs). Una tarea revisa cada cierto tiempo si cambio el periodo,
+ * y cuando cambia le paga al top del leaderboard de ese momento por el periodo que termino.
+ * Cada pago queda guardado con su periodo, si ya esta no se vuelve a pagar (solo en memoria, ver RewardPeriod en rules.rs)
+ */
+#[derive(Debug, Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+struct RankWinner {
+	rank: usize,
+	name: String,
+	coins: u32,
+	exp: u32,
+}
 
-	/*
-	 *  Seccion - Inicio
-	 *       Calculo integral
-	 */
+#[derive(Debug, Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+struct RankPayout {
+	period: String,
+	paid_at: DateTime<Utc>,
+	winners: Vec<RankWinner>,
+}
 
+type RankPayouts = Arc<RwLock<HashMap<String, RankPayout>>>; // periodo -> lo que se pago
 
-	let mut grades4_response = client
-		.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0167/1/notas/alumno")
-		.send()
-		.await?;
-	let mut grades4_text = grades4_response.text().await?;
-	if grades4_text.contains("No tienes permisos para ver esta") {
-		grades4_response = client.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0167/2/notas/alumno")
-		.send()
-		.await?;
-		grades4_text = grades4_response.text().await?;
+async fn pay_rank_rewards(period: &str, config: &RankRewards, level_system: &LevelSystem, storage: &StudentStorage, payouts: &RankPayouts) -> Option<RankPayout> {
+	let mut paid = payouts.write().await;
+	if paid.contains_key(period) {
+		return None; // Ya se pago este periodo
 	}
-	let mut attendance4_response = client
-		.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0167/1/asistencias2/")
-		.send()
-		.await?;
-	let mut attendance4_text = attendance4_response.text().await?;
-	if attendance4_text.contains("No tienes permisos para ver esta") {
-		attendance4_response = client.get("https://ucampus.uahurtado.cl/uah/2025/1/CSI0167/2/asistencias2/")
-		.send()
-		.await?;
-		attendance4_text = attendance4_response.text().await?;
+
+	let mut students = storage.write().await;
+	let ranking: Vec<String> = leaderboard_order(&students).into_iter()
+		.take(config.top)
+		.map(|(username, _)| username.clone())
+		.collect();
+	if ranking.is_empty() {
+		return None;
 	}
 
-	let mut base_4 = 1;
-	if grades4_text.contains("Examen") {
-		base_4 = base_4 + 1;
+	let mut winners = vec![];
+	for (i, username) in ranking.iter().enumerate() {
+		let Some(student) = students.get_mut(username) else {
+			continue;
+		};
+		// Igual que el prototipo, 50 * asistencia truncado
+		let attendance = student.assist as f32 / 100.0;
+		let coins = (config.coins as f32 * attendance).trunc() as u32;
+		let exp = (config.exp as f32 * attendance).trunc() as u32;
+
+		student.coins += coins;
+		student.exp += exp;
+		student.record_exp_change(EventKind::XpGrant, format!("Premio por puesto #{} ({})", i + 1, period), exp as i64, coins as i64, level_system);
+		student.check_achievements(false);
+
+		winners.push(RankWinner { rank: i + 1, name: student.name.clone(), coins, exp });
 	}
 
-	let mut calculointegral_grades = vec![];
+	let payout = RankPayout { period: period.to_string(), paid_at: Utc::now(), winners };
+	paid.insert(period.to_string(), payout.clone());
+	Some(payout)
+}
 
-	if let Some(grade) = extract_nth_between(&grades4_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_4 as usize) {
-		calculointegral_grades.push(grade);
-	}
-	if let Some(grade) = extract_nth_between(&grades4_text.to_string().replace("wrong", ""), r#"<h1 class="strong"><span class="">"#, "</span></h1>", base_4 + 1) {
-		calculointegral_grades.push(grade);
+// Tambien revisa las metas de equipo en cada vuelta, asi la experiencia que llega por scrapeos y compras se paga sola
+async fn rank_reward_job(rules: XpRules, level_system: LevelSystem, storage: StudentStorage, payouts: RankPayouts, teams: TeamStorage) {
+	let config = &rules.rank_rewards;
+	let mut current = config.period.key(Utc::now());
+	let mut interval = rocket::tokio::time::interval(std::time::Duration::from_secs(config.check_interval_seconds));
+	loop {
+		interval.tick().await;
+		{
+			let mut teams = teams.write().await;
+			let mut students = storage.write().await;
+			settle_team_goals(&mut teams, &mut students, &rules.teams);
+		}
+
+		let now = config.period.key(Utc::now());
+		if now == current {
+			continue;
+		}
+		if let Some(payout) = pay_rank_rewards(&current, config, &level_system, &storage, &payouts).await {
+			tracing::info!(period = %payout.period, winners = payout.winners.len(), "premio por puesto pagado");
+		}
+		current = now;
 	}
+}
 
+// Lo que responde /scrape ademas del estudiante
+struct ScrapeOutcome {
+	student: Student,
+	is_new_user: bool,
+	season: String,
+	failed: Vec<FailedCourse>,
+	stale: Vec<StaleCourse>,	// Cursos que se mostraron desde el cache porque ucampus no los entrego
+	new_achievements: Vec<Badge>,
+}
 
-	let acalculointegral_attendance = extract_attendance(&attendance4_text, "<th>Asistencia", "%</h1>")
-		.unwrap_or("0".to_string()).replace(">", "");
+/*
+ * El scrapeo en si esta en ucampus.rs detras de GradeSource, aquí solo se mezcla lo que llega con lo que el estudiante ya tenia.
+ * Si ucampus falla se usa el ultimo scrapeo bueno (cache.rs): los cursos que fallaron con un login valido se rellenan siempre,
+ * y si ni siquiera se pudo loguear se usa todo el cache si el ultimo login bueno es reciente y la contraseña coincide con la de ese login.
+ * Con contraseña mala no hay cache, ni aunque ucampus este caido
+ */
+async fn scrape_ucampus(source: &dyn GradeSource, form: &LoginForm, seasons: &SeasonState, rules: &XpRules, level_system: &LevelSystem, storage: &StudentStorage, scrape_cache: &ScrapeCache) -> Result<ScrapeOutcome, ScrapeError> {
 	/*
-	 *  Seccion - Fin
-	 *       Calculo integral
+	 * La temporada no queda tomada mientras se espera a ucampus (hasta SCRAPE_DEADLINE), si no un cierre pendiente frena
+	 * a todos los que leen. Se copia el catalogo y al volver se revisa que siga la misma temporada antes de guardar nada
 	 */
-
-	let name = extract_nth_between(&grades1_text, "alias: '", "',", 1)
-		.unwrap_or("Desconocido".to_string()); //De aquí sale el nombre del estudiante
-
-
-	let mut student = Student {
-		name,
-		assist: 0,
-		grades: 0,
-		mean: 0.0,
-		exp: 0,
-		level: 1,
-		penalty: 0,
-		bonus: 0,
-		coins: 100,
+	let LoginForm { username, password } = form;
+	let (season_id, catalog) = {
+		let seasons = seasons.read().await;
+		(seasons.current.id.clone(), seasons.current.catalog.clone())
 	};
+	let result = source.fetch_student(username, password, &catalog).await;
+	let now = Utc::now();
 
-	//Convertimos todas las asistencias en flotantes para el vector, en caso de no poder convertirlo se pone 0.0
-	let electivo_att_val = electivo_attendance.parse::<f32>().unwrap_or(0.0); 
-	let habilidades_att_val = habilidades_attendance.parse::<f32>().unwrap_or(0.0);
-	let algebralineal_att_val = algebralineal_attendance.parse::<f32>().unwrap_or(0.0);
-	let calculointegral_att_val = acalculointegral_attendance.parse::<f32>().unwrap_or(0.0);
-
-
-	//Super debug information
-	println!("Elect_val, habil_val {:?},{:?}", electivo_att_val, habilidades_att_val);
-	println!(" Username: {:?}\n Grades_Electivo_raw|AsistenciaR: {:?} | {:?}\n Grades_Habilidades_raw|Asistencia: {:?} | {:?}", username,electivo_grades, electivo_attendance, habilidades_grades, habilidades_attendance);
-	print!(" Grades_Algebra_lineal_raw|Asistencia: {:?} | {:?}\n Grades_Calculo_integral_raw|Asistencia: {:?} | {:?}\n==============================\n", algebralineal_grades, algebralineal_attendance, calculointegral_grades, acalculointegral_attendance);
-
-
-	//Aplicacion de el sistema de nivel para cada materia, se le pasa el vector de notas, la asistencia y el sistema de niveles
-	let level_system = LevelSystem;
-	student.apply_full_gamification(
-		&electivo_grades, 
-		electivo_att_val,
-		&habilidades_grades, 
-		habilidades_att_val,
-		&algebralineal_grades, 
-		algebralineal_att_val,
-		&calculointegral_grades, 
-		calculointegral_att_val,
-		&level_system
-	);
-
-	Ok(student)
-}
-
-/*
-* def extract_between_r( s, first, last ):
-*	 start = s.rindex( first ) + len( first )
-*	 end = s.rindex( last, start )
-*	 return s[start:end]
-*/
-// Lo que en python hacia con assitencia2 = assaignment2Data.split("<th>Asistencia")[1].split("%</h1>")[0][-3:].replace(">","") aquí tengo que usar esta funcion, nacida a base de find_between_r de https://stackoverflow.com/questions/3368969/find-string-between-two-substrings
-
-fn extract_nth_between(text: &str, start: &str, end: &str, n: usize) -> Option<String> {
-	let mut current_pos = 0;
-	for i in 1..=n {
-		if let Some(start_idx) = text[current_pos..].find(start) {
-			let start_pos = start_idx + current_pos + start.len();
-			if let Some(end_idx) = text[start_pos..].find(end) {
-				let end_pos = end_idx + start_pos;
-				if i == n {
-					return Some(text[start_pos..end_pos].to_string());
-				}
-				current_pos = end_pos + end.len();
-			} else {
-				return None;
-			}
-		} else {
-			return None;
-		}
+	let seasons = seasons.read().await;
+	if seasons.current.id != season_id {
+		return Err(ScrapeError::SeasonChanged);
 	}
-	None
-}
+	let mut caches = scrape_cache.write().await;
+
+	let (scraped, stale) = match result {
+		Ok(mut scraped) => {
+			let cache = caches.entry(username.to_string()).or_default();
+			let fresh = scraped.clone();
+			let stale = cache.fill_failed(&mut scraped, now);
+			if scraped.courses.is_empty() {
+				// Login bueno pero no llego ningun curso y no hay nada guardado, se devuelve el error del primero
+				return Err(scraped.first_error.unwrap_or(ScrapeError::Parse("no se encontraron cursos".to_string())));
+			}
+			cache.store(&fresh, password, now);
+			(scraped, stale)
+		},
+		Err(ScrapeError::BadCredentials) => return Err(ScrapeError::BadCredentials),
+		Err(e) => match caches.get(username).and_then(|cache| cache.offline_copy(password, now)) {
+			Some(offline) => {
+				tracing::warn!(error = %e, "ucampus no disponible, se usan los datos guardados");
+				offline
+			},
+			None => return Err(e),
+		},
+	};
+	drop(caches);
 
-// Extrae la asistencia, es como la otra funcion pero devuelve los ultimos 3 caracteres
-fn extract_attendance(text: &str, start: &str, end: &str) -> Option<String> {
-	let start_pos = text.find(start)? + start.len();
-	let end_pos = text[start_pos..].find(end)? + start_pos;
-	let full_text = &text[start_pos..end_pos];
-	
-	if full_text.len() >= 3 {
-		Some(full_text[full_text.len()-3..].to_string())
-	} else {
-		Some(full_text.to_string())
-	}
+	let mut students = storage.write().await;
+	let is_new_user = !students.contains_key(username);
+	let name = scraped.name.unwrap_or("Desconocido".to_string());
+	let student = students.entry(username.to_string()).or_insert_with(|| Student::new(name.clone()));
+	student.name = name;
+
+	//Aplicacion de el sistema de nivel para cada materia, se le pasa la lista de cursos, las reglas de experiencia y el sistema de niveles
+	student.apply_full_gamification(&scraped.courses, rules, level_system);
+	let new_achievements = student.check_achievements(false);
+
+	Ok(ScrapeOutcome {
+		season: season_id,
+		student: student.clone(),
+		is_new_user,
+		failed: scraped.failed,
+		stale,
+		new_achievements,
+	})
 }
 
 #[get("/")]
@@ -772,6 +955,22 @@ fn index() -> RawHtml<&'static str> {
 				border-radius: 3px;
 				border: 1px solid #ddd;
 			}
+			.course-breakdown {
+				margin: 10px 0;
+				padding: 10px;
+				background: white;
+				border-radius: 3px;
+				border: 1px solid #ddd;
+			}
+			.course-breakdown h4 {
+				margin: 0 0 5px 0;
+			}
+			.tier-hit {
+				color: #155724;
+			}
+			.penalty-hit {
+				color: #dc3545;
+			}
 			.loading {
 				text-align: center;
 				color: #666;
@@ -794,6 +993,19 @@ fn index() -> RawHtml<&'static str> {
 				text-align: center;
 				border: 1px solid #ddd;
 			}
+			.level-progress {
+				margin-top: 15px;
+			}
+			.level-bar {
+				background: #ddd;
+				border-radius: 5px;
+				height: 10px;
+				overflow: hidden;
+			}
+			.level-bar div {
+				background: rgb(0, 123, 255);
+				height: 100%;
+			}
 		</style>
 	</head>
 	<body>
@@ -805,6 +1017,8 @@ fn index() -> RawHtml<&'static str> {
 				<a href="/coinflip" class="nav-button">🪙 Coin Flip Game</a>
 				<a href="/shop" class="nav-button">🛒 Tienda</a>
 				<a href="/slots" class="nav-button">🎰 Slots</a>
+				<a href="/teams" class="nav-button">👥 Equipos</a>
+				<a href="/what-if" class="nav-button">🔮 Simulador</a>
 			</div>
 			
 			<form id="loginForm">
@@ -848,6 +1062,9 @@ fn index() -> RawHtml<&'static str> {
 					if (response.ok) {
 						const data = await response.json();
 						displayResults(data);
+						loadBadges(formData.get('username'));
+						loadSeasons(formData.get('username'));
+						loadTimeline(formData.get('username'));
 					} else {
 						const error = await response.text();
 						resultDiv.innerHTML = `<div class="result error">Error: ${error}</div>`;
@@ -860,33 +1077,219 @@ fn index() -> RawHtml<&'static str> {
 				}
 			});
 			
-			function displayResults(data) {
-				const resultDiv = document.getElementById('result');
-				let statusMessage = data.is_new_user ? 
-					'<div class="result success">¡Bienvenido! Tu cuenta ha sido registrada en el sistema.</div>' :
-					'<div class="result">Datos actualizados en el sistema.</div>';
-				
-				resultDiv.innerHTML = statusMessage + `
-					<div class="result">
-						<h2>Estudiante: ${data.name}</h2>
-						
-						<div class="grade-section">
-							<h3>Rendimiento academico</h3>
-							<div class="grade-item">Puntos totales por notas: ${data.grades}</div>
-							<div class="grade-item">Promedio [Electivo, Habilidades, Algebra, Calculo]: ${data.mean.toFixed(2)}</div>
-							<div class="grade-item">Asistencia: ${data.assist}%</div>
+			function formatAge(seconds) {
+				if (seconds < 60) return 'unos segundos';
+				if (seconds < 3600) return Math.floor(seconds / 60) + ' minutos';
+				if (seconds < 86400) return Math.floor(seconds / 3600) + ' horas';
+				return Math.floor(seconds / 86400) + ' días';
+			}
+			
+			// Desglose por curso: de donde salio la experiencia y las monedas de cada uno
+			function metricLabel(metric) {
+				return metric === 'grade' ? 'Promedio' : 'Asistencia';
+			}
+			
+			function formatThreshold(metric, value) {
+				return metric === 'grade' ? value.toFixed(1) : value + '%';
+			}
+			
+			function renderCourseBreakdown(courses) {
+				const codes = Object.keys(courses || {}).sort();
+				if (codes.length === 0) return '';
+				return codes.map(code => {
+					const c = courses[code];
+					const tiers = c.tiers.map(t => `<div class="tier-hit">✔ ${metricLabel(t.metric)} ≥ ${formatThreshold(t.metric, t.min)}: +${t.exp} XP, +${t.bonus} bonus</div>`).join('');
+					const penalties = c.penalties.map(p => `<div class="penalty-hit">✘ ${metricLabel(p.metric)} ≤ ${formatThreshold(p.metric, p.at_most)}: -${p.exp} XP, severidad ${p.severity}</div>`).join('');
+					return `
+						<div class="course-breakdown">
+							<h4>${code}</h4>
+							<div>Promedio: ${c.mean.toFixed(2)} | Asistencia: ${c.attendance.toFixed(0)}%</div>
+							<div>Base por promedio y asistencia: +${c.base_exp} XP</div>
+							${tiers}
+							${penalties}
+							${c.improvement_exp > 0 ? `<div class="tier-hit">📈 Mejora: +${c.improvement_exp} XP</div>` : ''}
+							<div><strong>XP ganada: ${c.awarded_exp + c.improvement_exp - c.penalty_exp} | Monedas: ${c.coins_minted} 🪙</strong></div>
 						</div>
-						
-						<div class="result gamification">
-							<h3>🎮 Estadisticas de gamificacion</h3>
-							<div class="stats">
-								<div class="stat-item">
-									<strong>Nivel</strong><br>
-									${data.level}
-								</div>
-								<div class="stat-item">
-									<strong>Experiencia</strong><br>
-									${data.exp} XP
+					`;
+				}).join('');
+			}
+			
+			// Logros desbloqueados y los que faltan, los bloqueados salen en gris
+			async function loadBadges(username) {
+				const response = await fetch(`/achievements/${encodeURIComponent(username)}`);
+				if (!response.ok) return;
+				const data = await response.json();
+				const unlocked = data.unlocked.map(b => `<div class="grade-item">${b.icon} <strong>${b.name}</strong>: ${b.description} (${new Date(b.unlocked_at).toLocaleDateString()})</div>`).join('');
+				const locked = data.locked.map(b => `<div class="grade-item" style="opacity: 0.5">🔒 ${b.name}: ${b.description}</div>`).join('');
+				document.getElementById('badges').innerHTML = unlocked + locked;
+			}
+			
+			// Resultados de las temporadas anteriores, si no jugo ninguna no se muestra nada
+			async function loadSeasons(username) {
+				const response = await fetch(`/students/${encodeURIComponent(username)}/seasons`);
+				if (!response.ok) return;
+				const history = await response.json();
+				if (history.length === 0) return;
+				document.getElementById('seasons').innerHTML = '<h3>📚 Temporadas anteriores</h3>' + history.map(h => `
+					<div class="grade-item">
+						<strong>${h.season}</strong>: puesto ${h.standing.rank} de ${h.players} | Nivel ${h.standing.level} | ${h.standing.exp} XP | ${h.standing.coins} 🪙 | Promedio ${h.standing.mean.toFixed(2)}
+					</div>
+				`).join('');
+			}
+			
+			// Progreso: una linea con la experiencia en el tiempo y los ultimos eventos
+			const eventIcons = { xp_grant: '⬆️', penalty: '⚠️', level_up: '🎉', purchase: '🛒', game: '🎲', team_goal: '👥', check_in: '🔥' };
+			
+			function formatDelta(value, unit) {
+				if (value === 0) return '';
+				return `${value > 0 ? '+' : ''}${value} ${unit}`;
+			}
+			
+			async function loadTimeline(username) {
+				const response = await fetch(`/students/${encodeURIComponent(username)}/timeline`);
+				if (!response.ok) return;
+				const events = await response.json();
+				if (events.length === 0) return;
+				
+				const width = 500, height = 80;
+				const maxExp = Math.max(...events.map(e => e.exp), 1);
+				const points = events.map((e, i) => {
+					const x = events.length === 1 ? width : (i / (events.length - 1)) * width;
+					const y = height - (e.exp / maxExp) * height;
+					return `${x.toFixed(1)},${y.toFixed(1)}`;
+				}).join(' ');
+				const chart = `<svg viewBox="0 0 ${width} ${height}" style="width: 100%; height: 80px; background: white; border: 1px solid #ddd;">
+					<polyline fill="none" stroke="rgb(0, 123, 255)" stroke-width="2" points="${points}"/>
+				</svg>`;
+				
+				const recent = events.slice(-15).reverse().map(e => `
+					<div class="grade-item">
+						${eventIcons[e.kind] || ''} <small>${new Date(e.at).toLocaleString()}</small> ${e.description}
+						<strong>${[formatDelta(e.exp_delta, 'XP'), formatDelta(e.coins_delta, '🪙')].filter(d => d).join(', ')}</strong>
+					</div>
+				`).join('');
+				document.getElementById('timeline').innerHTML = '<h3>📈 Progreso</h3>' + chart + recent;
+			}
+			
+			// Un castigo por linea, con la severidad en ● para que se note cuanto pesa
+			function renderActivePenalties(penalties) {
+				if (!penalties || penalties.length === 0) {
+					return '<div class="tier-hit">Sin castigos activos 🎉</div>';
+				}
+				return penalties.map(p => `
+					<div class="penalty-hit">
+						${'●'.repeat(p.severity)}${'○'.repeat(Math.max(0, 3 - p.severity))} ${p.course}: ${metricLabel(p.metric)} ≤ ${formatThreshold(p.metric, p.at_most)},
+						severidad ${p.severity} (-${p.exp} XP) desde ${new Date(p.since).toLocaleDateString()}
+					</div>
+				`).join('') + '<div><small>El castigo se levanta solo cuando el curso vuelve a estar sobre el tramo</small></div>';
+			}
+			
+			function renderStreak(current, longest, freezes) {
+				return `<div class="grade-item">Racha: ${current} ${current === 1 ? 'día' : 'días'} 🔥 | Mejor racha: ${longest} | Protectores: ${freezes} 🧊</div>`;
+			}
+			
+			async function dailyCheckIn() {
+				const username = document.getElementById('username').value;
+				const checkinDiv = document.getElementById('checkin');
+				const response = await fetch('/check-in', {
+					method: 'POST',
+					headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+					body: new URLSearchParams({ username })
+				});
+				if (!response.ok) {
+					checkinDiv.innerHTML += `<div class="result error">${await response.text()}</div>`;
+					return;
+				}
+				const data = await response.json();
+				checkinDiv.innerHTML = renderStreak(data.streak, data.longest, data.freezes_left) +
+					`<div class="result success">${data.message}: +${data.coins} 🪙, +${data.exp} XP (nivel ${data.level})</div>` +
+					data.new_achievements.map(b => `<div class="grade-item">🏆 Nuevo logro: ${b.icon} ${b.name}</div>`).join('');
+			}
+			
+			// Barra de avance al siguiente nivel
+			function renderLevelProgress(progress) {
+				if (!progress) {
+					return '';
+				}
+				if (progress.next_level_exp === null) {
+					return '<div class="level-progress">Nivel máximo alcanzado 🎉</div>';
+				}
+				return `
+					<div class="level-progress">
+						<div>Nivel ${progress.level + 1}: ${progress.exp - progress.level_exp} / ${progress.next_level_exp - progress.level_exp} XP (${progress.percent.toFixed(0)}%)</div>
+						<div class="level-bar"><div style="width: ${progress.percent}%"></div></div>
+					</div>
+				`;
+			}
+			
+			function displayResults(data) {
+				const resultDiv = document.getElementById('result');
+				let statusMessage = data.is_new_user ? 
+					'<div class="result success">¡Bienvenido! Tu cuenta ha sido registrada en el sistema.</div>' :
+					'<div class="result">Datos actualizados en el sistema.</div>';
+				if (data.stale_courses && data.stale_courses.length > 0) {
+					statusMessage += '<div class="result error">U-Campus no respondió, estos cursos se muestran con los últimos datos guardados:' +
+						data.stale_courses.map(c => `<div class="grade-item">${c.code}: datos de hace ${formatAge(c.age_seconds)} (${new Date(c.fetched_at).toLocaleString()})</div>`).join('') +
+						'</div>';
+				}
+				if (data.new_achievements && data.new_achievements.length > 0) {
+					statusMessage += '<div class="result success">¡Nuevos logros desbloqueados!' +
+						data.new_achievements.map(b => `<div class="grade-item">${b.icon} ${b.name}</div>`).join('') +
+						'</div>';
+				}
+				if (data.failed_courses && data.failed_courses.length > 0) {
+					statusMessage += '<div class="result error">No se pudieron cargar algunos cursos:' +
+						data.failed_courses.map(c => `<div class="grade-item">${c.name} (${c.code}): ${c.reason}</div>`).join('') +
+						'</div>';
+				}
+				
+				resultDiv.innerHTML = statusMessage + `
+					<div class="result">
+						<h2>Estudiante: ${data.name}</h2>
+						<div>Temporada ${data.season}</div>
+						
+						<div class="grade-section">
+							<h3>Rendimiento academico</h3>
+							<div class="grade-item">Puntos totales por notas: ${data.grades}</div>
+							<div class="grade-item">Promedio general de los cursos: ${data.mean.toFixed(2)}</div>
+							<div class="grade-item">Asistencia: ${data.assist}%</div>
+						</div>
+						
+						<div class="grade-section">
+							<h3>Detalle por curso</h3>
+							${renderCourseBreakdown(data.courses)}
+						</div>
+						
+						<div class="grade-section">
+							<h3>🔥 Check-in diario</h3>
+							<div id="checkin">${renderStreak(data.streak_active, data.streak.longest, data.streak.freezes)}</div>
+							<button onclick="dailyCheckIn()">Marcar el check-in de hoy</button>
+						</div>
+						
+						<div class="grade-section">
+							<h3>⚠️ Castigos activos</h3>
+							${renderActivePenalties(data.active_penalties)}
+						</div>
+						
+						<div class="grade-section">
+							<h3>🏆 Logros</h3>
+							<div id="badges"></div>
+						</div>
+						
+						<div class="grade-section" id="timeline"></div>
+						
+						<div class="grade-section" id="seasons"></div>
+						
+						<div class="result gamification">
+							<h3>🎮 Estadisticas de gamificacion</h3>
+							<div class="stats">
+								<div class="stat-item">
+									<strong>Nivel</strong><br>
+									${data.level}${data.title ? `<br><small>${data.title}</small>` : ''}
+								</div>
+								<div class="stat-item">
+									<strong>Experiencia</strong><br>
+									${data.exp} XP
 								</div>
 								<div class="stat-item">
 									<strong>Monedas</strong><br>
@@ -901,6 +1304,7 @@ fn index() -> RawHtml<&'static str> {
 									${data.penalty}
 								</div>
 							</div>
+							${renderLevelProgress(data.level_progress)}
 						</div>
 					</div>
 				`;
@@ -912,20 +1316,35 @@ fn index() -> RawHtml<&'static str> {
 }
 
 
-#[get("/leaderboard")]
-async fn leaderboard(storage: &State<StudentStorage>) -> RawHtml<String> {
-	let students = storage.read().await; // Lectura al almacenamiento de estudiantes supuestamente threadsafe
-
-	// Clona los datos de los estudiantes en un vector para poder ordenarlos
-	let mut sorted_students: Vec<_> = students.values().cloned().collect();
-
+// Los iconos de los logros desbloqueados, con el nombre al pasar el mouse
+fn badge_icons(student: &Student) -> String {
+	let (unlocked, _) = achievements::badges(&student.achievements);
+	unlocked.iter()
+		.map(|badge| format!(r#"<span title="{}">{}</span>"#, badge.name, badge.icon))
+		.collect::<Vec<_>>()
+		.join("")
+}
 
- 	// Ordena los estudiantes: primero por experiencia, luego por nivel, luego por monedas
-	sorted_students.sort_by(|a, b| {
+// Ordena los estudiantes: primero por experiencia, luego por nivel, luego por monedas (el correo al final para que los empates no cambien de orden)
+fn leaderboard_order(students: &HashMap<String, Student>) -> Vec<(&String, &Student)> {
+	let mut sorted: Vec<_> = students.iter().collect();
+	sorted.sort_by(|(user_a, a), (user_b, b)| {
 		b.exp.cmp(&a.exp)
 			.then_with(|| b.level.cmp(&a.level))
 			.then_with(|| b.coins.cmp(&a.coins))
+			.then_with(|| user_a.cmp(user_b))
 	});
+	sorted
+}
+
+#[get("/leaderboard")]
+async fn leaderboard(storage: &State<StudentStorage>, rules: &State<XpRules>, seasons: &State<SeasonState>) -> RawHtml<String> {
+	let season_id = seasons.read().await.current.id.clone();
+	let students = storage.read().await; // Lectura al almacenamiento de estudiantes supuestamente threadsafe
+
+	let sorted_students: Vec<&Student> = leaderboard_order(&students).into_iter().map(|(_, student)| student).collect();
+	let rank_rewards = &rules.rank_rewards;
+	let today = checkin::local_day(Utc::now());
 
 
 	//Generar el html de la tabla sorted_students, despues el string se inyecta en RawHtml cuando hay ya estudiantes participando, de lo contrario div de estudiantes registrados = 0 será mostrado en pantalla
@@ -942,23 +1361,33 @@ async fn leaderboard(storage: &State<StudentStorage>) -> RawHtml<String> {
 			format!(
 				r#"
 				<div class="leaderboard-item rank-{}">
+					{}
 					<span class="rank">{} #{}</span>
 					<span class="name">{}</span>
+					<span class="badges">{}</span>
 					<div class="stats">
-						<span>Nivel: {}</span>
+						<span>Nivel: {}{}</span>
 						<span>EXP: {}</span>
 						<span>Monedas: {}</span>
+						{}
 						<span class="average-toggle" onclick="toggleAverage(this)" data-value="{:.2}">Copuchentear</span>
 					</div>
 				</div>
 				"#,
 				if i < 3 { "top" } else { "normal" }, // Cambia el estilo dependiendo del puesto
+				if i < rank_rewards.top { r#"<span title="Recibe premio por puesto al cerrar el periodo">💰</span>"# } else { "" },
 				rank_emoji,
 				i + 1,
 				student.name,
+				badge_icons(student),
 				student.level,
+				student.title.as_ref().map(|title| format!(" ({})", title)).unwrap_or_default(),
 				student.exp,
 				student.coins,
+				match student.streak.active(today) {
+					0 => String::new(),
+					streak => format!(r#"<span title="Dias seguidos con check-in">🔥 {}</span>"#, streak),
+				},
 				student.mean
 			)
 		})
@@ -1035,6 +1464,10 @@ async fn leaderboard(storage: &State<StudentStorage>) -> RawHtml<String> {
 				margin: 0 20px;
 				color: #333;
 			}}
+			.badges {{
+				margin-right: 20px;
+				font-size: 1.2em;
+			}}
 			.stats {{
 				display: flex;
 				gap: 15px;
@@ -1072,55 +1505,853 @@ async fn leaderboard(storage: &State<StudentStorage>) -> RawHtml<String> {
 			}}
 			.empty-state {{
 				text-align: center;
-				color: #666;
-				padding: 40px;
-				font-style: italic;
-			}}
+				color: #666;
+				padding: 40px;
+				font-style: italic;
+			}}
+		</style>
+		<script>
+			function toggleAverage(element) {{
+				if (element.classList.contains('revealed')) {{
+					// If already revealed, hide it again
+					element.textContent = 'Copuchentear';
+					element.classList.remove('revealed');
+				}} else {{
+					// Reveal the actual grade
+					const actualValue = element.getAttribute('data-value');
+					element.textContent = 'Promedio: ' + actualValue;
+					element.classList.add('revealed');
+				}}
+			}}
+		</script>
+	</head>
+	<body>
+		<div class="container">
+			<h1>🏆 Leaderboard - Top Estudiantes</h1>
+			<p style="text-align: center; color: #666;">Temporada {}</p>
+			
+			<div class="navigation">
+				<a href="/" class="nav-button">🏠 Inicio</a>
+				<a href="/coinflip" class="nav-button">🪙 Coin Flip Game</a>
+				<a href="/shop" class="nav-button">🛒 Tienda</a>
+				<a href="/slots" class="nav-button">🎰 Slots</a>
+				<a href="/teams" class="nav-button">👥 Equipos</a>
+				<a href="/what-if" class="nav-button">🔮 Simulador</a>
+			</div>
+			
+			<p style="text-align: center; color: #666;">💰 Los primeros {} reciben hasta {} monedas y {} XP ({}) según su asistencia</p>
+			
+			<div class="leaderboard">
+				{}
+			</div>
+			
+			{}
+		</div>
+	</body>
+	</html>
+	"#, 
+	season_id,
+	rank_rewards.top,
+	rank_rewards.coins,
+	rank_rewards.exp,
+	match rank_rewards.period {
+		RewardPeriod::Hourly => "cada hora",
+		RewardPeriod::Daily => "cada día",
+		RewardPeriod::Weekly => "cada semana",
+	},
+
+	if leaderboard_html.is_empty() {
+		r#"<div class="empty-state">No hay estudiantes registrados aún. ¡Sé el primero en unirte!</div>"#.to_string()
+	} else {
+		leaderboard_html
+	},
+	if sorted_students.is_empty() {
+		"".to_string()
+	} else {
+		format!(r#"<p style="text-align: center; margin-top: 20px; color: #666;"><small>Total de estudiantes registrados: {}</small></p>"#, sorted_students.len())
+	}))
+}
+
+/*
+ * Equipos (teams.rs). El puntaje se calcula cada vez con los datos actuales de los integrantes,
+ * y las metas se revisan con cada cambio de integrantes y en la tarea periodica de rank_reward_job
+ */
+fn team_totals<'a>(members: impl IntoIterator<Item = &'a String>, students: &HashMap<String, Student>, rules: &TeamRules) -> TeamTotals {
+	let members: Vec<(u32, u8)> = members.into_iter()
+		.filter_map(|username| students.get(username))
+		.map(|student| (student.exp, student.assist))
+		.collect();
+	TeamTotals::from_members(&members, rules)
+}
+
+/*
+ * Paga las metas cumplidas. Solo cuentan y cobran los integrantes con el tiempo minimo en el equipo, y cada estudiante
+ * cobra cada meta una vez por temporada (Student::team_goals), aunque cambie de equipo o el equipo se borre y se vuelva a armar
+ */
+fn settle_team_goals(teams: &mut Teams, students: &mut HashMap<String, Student>, rules: &TeamRules) {
+	let now = Utc::now();
+	for lobby in teams.lobbies.values_mut() {
+		let settled: Vec<String> = lobby.settled_members(now, rules).into_iter().cloned().collect();
+		let totals = team_totals(&settled, students, rules);
+		for goal in rules.goals.iter().filter(|goal| totals.reached(goal)) {
+			lobby.paid_goals.entry(goal.id.clone()).or_insert(now);
+			let mut paid = 0;
+			for username in &settled {
+				let Some(student) = students.get_mut(username) else {
+					continue;
+				};
+				if student.team_goals.contains_key(&goal.id) {
+					continue;
+				}
+				student.team_goals.insert(goal.id.clone(), now);
+				student.coins += goal.coins;
+				student.record(EventKind::TeamGoal, format!("Meta de equipo {}: {}", lobby.name, goal.name), 0, goal.coins as i64);
+				paid += 1;
+			}
+			if paid > 0 {
+				tracing::info!(team = %lobby.id, goal = %goal.id, paid, "meta de equipo pagada");
+			}
+		}
+	}
+}
+
+// Un equipo como sale en la API y en la pagina, con nombres y no correos
+fn team_view(lobby: &Lobby, students: &HashMap<String, Student>, rules: &TeamRules) -> serde_json::Value {
+	let totals = team_totals(&lobby.members, students, rules);
+	// El avance de las metas es solo con los que ya cumplen el tiempo minimo en el equipo
+	let goal_totals = team_totals(lobby.settled_members(Utc::now(), rules), students, rules);
+	let goals: Vec<serde_json::Value> = rules.goals.iter()
+		.map(|goal| serde_json::json!({
+			"id": goal.id,
+			"name": goal.name,
+			"target": goal.target,
+			"coins": goal.coins,
+			"progress": (goal_totals.value(goal.metric) / goal.target * 100.0).min(100.0),
+			"paid_at": lobby.paid_goals.get(&goal.id),
+		}))
+		.collect();
+	serde_json::json!({
+		"id": lobby.id,
+		"name": lobby.name,
+		"created_at": lobby.created_at,
+		"members": lobby.members.iter().map(|u| students.get(u).map(|s| s.name.clone()).unwrap_or("Sin ingresar".to_string())).collect::<Vec<_>>(),
+		"totals": totals,
+		"goals": goals,
+	})
+}
+
+//API - Equipos ordenados por puntaje
+#[get("/teams/list")]
+async fn get_teams(teams: &State<TeamStorage>, storage: &State<StudentStorage>, rules: &State<XpRules>) -> Json<Vec<serde_json::Value>> {
+	let teams = teams.read().await;
+	let students = storage.read().await;
+	let mut lobbies: Vec<(f32, serde_json::Value)> = teams.lobbies.values()
+		.map(|lobby| (team_totals(&lobby.members, &students, &rules.teams).score, team_view(lobby, &students, &rules.teams)))
+		.collect();
+	lobbies.sort_by(|a, b| b.0.total_cmp(&a.0));
+	Json(lobbies.into_iter().map(|(_, view)| view).collect())
+}
+
+fn team_error(status: rocket::http::Status, message: String) -> rocket::response::status::Custom<String> {
+	rocket::response::status::Custom(status, message)
+}
+
+enum TeamAction {
+	Create,
+	Join,
+	Leave,
+	Assign,
+}
+
+// Lo comun de crear, unirse, salir y asignar: validar, cambiar el equipo, revisar metas y responder como quedo
+async fn change_team(action: TeamAction, form: &TeamForm, teams: &TeamStorage, storage: &StudentStorage, rules: &TeamRules) -> Result<Json<serde_json::Value>, rocket::response::status::Custom<String>> {
+	let mut teams = teams.write().await;
+	let mut students = storage.write().await;
+
+	// Los profes pueden asignar a alguien que todavia no ingresa, el resto tiene que haber ingresado
+	if !matches!(action, TeamAction::Assign) && !students.contains_key(&form.username) {
+		return Err(team_error(rocket::http::Status::NotFound, "Estudiante no encontrado. Ingresa a la plataforma primero.".to_string()));
+	}
+	let team = form.team.as_deref().unwrap_or_default();
+	if !matches!(action, TeamAction::Leave) && team.trim().is_empty() {
+		return Err(team_error(rocket::http::Status::BadRequest, "Falta el equipo".to_string()));
+	}
+
+	let result = match action {
+		TeamAction::Create => teams.create(team, &form.username, rules).map(|lobby| lobby.id.clone()),
+		TeamAction::Join => teams.join(team, &form.username, rules).map(|lobby| lobby.id.clone()),
+		TeamAction::Assign => teams.assign(&form.username, team, rules).map(|lobby| lobby.id.clone()),
+		TeamAction::Leave => teams.leave(&form.username).map(|_| String::new()),
+	};
+	let id = result.map_err(|e| team_error(rocket::http::Status::BadRequest, e))?;
+
+	settle_team_goals(&mut teams, &mut students, rules);
+	match teams.lobbies.get(&id) {
+		Some(lobby) => Ok(Json(team_view(lobby, &students, rules))),
+		None => Ok(Json(serde_json::json!({ "message": "Saliste del equipo" }))),
+	}
+}
+
+#[post("/teams/create", data = "<form>")]
+async fn create_team(form: Form<TeamForm>, teams: &State<TeamStorage>, storage: &State<StudentStorage>, rules: &State<XpRules>, request_id: RequestId) -> Result<Json<serde_json::Value>, rocket::response::status::Custom<String>> {
+	let response = change_team(TeamAction::Create, &form, teams, storage, &rules.teams).await?;
+	tracing::info!(request_id = %request_id, user = %redact_email(&form.username), "equipo creado");
+	Ok(response)
+}
+
+#[post("/teams/join", data = "<form>")]
+async fn join_team(form: Form<TeamForm>, teams: &State<TeamStorage>, storage: &State<StudentStorage>, rules: &State<XpRules>, request_id: RequestId) -> Result<Json<serde_json::Value>, rocket::response::status::Custom<String>> {
+	let response = change_team(TeamAction::Join, &form, teams, storage, &rules.teams).await?;
+	tracing::info!(request_id = %request_id, user = %redact_email(&form.username), "se unio a un equipo");
+	Ok(response)
+}
+
+#[post("/teams/leave", data = "<form>")]
+async fn leave_team(form: Form<TeamForm>, teams: &State<TeamStorage>, storage: &State<StudentStorage>, rules: &State<XpRules>, request_id: RequestId) -> Result<Json<serde_json::Value>, rocket::response::status::Custom<String>> {
+	let response = change_team(TeamAction::Leave, &form, teams, storage, &rules.teams).await?;
+	tracing::info!(request_id = %request_id, user = %redact_email(&form.username), "salio de su equipo");
+	Ok(response)
+}
+
+// Para profes, con el header X-Admin-Token
+#[post("/teams/assign", data = "<form>")]
+async fn assign_team(_admin: Admin, form: Form<TeamForm>, teams: &State<TeamStorage>, storage: &State<StudentStorage>, rules: &State<XpRules>, request_id: RequestId) -> Result<Json<serde_json::Value>, rocket::response::status::Custom<String>> {
+	let response = change_team(TeamAction::Assign, &form, teams, storage, &rules.teams).await?;
+	tracing::info!(request_id = %request_id, user = %redact_email(&form.username), "equipo asignado por un profe");
+	Ok(response)
+}
+
+#[get("/teams")]
+fn teams_page() -> RawHtml<&'static str> {
+	RawHtml(r#"
+	<!DOCTYPE html>
+	<html>
+	<head>
+		<title>Equipos - UCampus EduGame</title>
+		<style>
+			body {
+				font-family: Arial, sans-serif;
+				max-width: 800px;
+				margin: 50px auto;
+				padding: 20px;
+				background: #f5f5f5;
+			}
+			.container {
+				background: white;
+				padding: 30px;
+				border-radius: 10px;
+				box-shadow: 0 2px 10px rgba(0,0,0,0.1);
+			}
+			h1 {
+				color: #333;
+				text-align: center;
+				margin-bottom: 30px;
+			}
+			.navigation {
+				text-align: center;
+				margin-bottom: 30px;
+			}
+			.nav-button {
+				display: inline-block;
+				margin: 0 10px 10px;
+				padding: 10px 20px;
+				background: #007bff;
+				color: white;
+				text-decoration: none;
+				border-radius: 5px;
+				transition: background 0.3s;
+			}
+			.nav-button:hover {
+				background: #0056b3;
+			}
+			.form-group {
+				margin-bottom: 15px;
+			}
+			input[type="email"], input[type="text"] {
+				width: 100%;
+				padding: 12px;
+				border: 1px solid #ddd;
+				border-radius: 5px;
+				box-sizing: border-box;
+				font-size: 16px;
+			}
+			.actions {
+				display: flex;
+				gap: 10px;
+				margin-bottom: 20px;
+			}
+			.actions button {
+				flex: 1;
+				padding: 10px;
+				background: #28a745;
+				color: white;
+				border: none;
+				border-radius: 5px;
+				cursor: pointer;
+			}
+			.actions button.leave {
+				background: #dc3545;
+			}
+			.team {
+				border: 2px solid #ddd;
+				border-radius: 10px;
+				padding: 15px;
+				margin-bottom: 15px;
+			}
+			.team.top {
+				border-color: #ffc107;
+				background: #fffbf0;
+			}
+			.team h3 {
+				margin: 0 0 5px 0;
+			}
+			.team small {
+				color: #666;
+			}
+			.goal {
+				margin-top: 8px;
+			}
+			.bar {
+				height: 8px;
+				background: #eee;
+				border-radius: 4px;
+				overflow: hidden;
+			}
+			.bar div {
+				height: 100%;
+				background: #28a745;
+			}
+			.result {
+				margin-bottom: 20px;
+				padding: 15px;
+				border-radius: 5px;
+			}
+			.success {
+				background: #d4edda;
+				color: #155724;
+			}
+			.error {
+				background: #f8d7da;
+				color: #721c24;
+			}
+			.empty-state {
+				text-align: center;
+				color: #666;
+				padding: 40px;
+				font-style: italic;
+			}
+		</style>
+	</head>
+	<body>
+		<div class="container">
+			<h1>👥 Equipos</h1>
+			
+			<div class="navigation">
+				<a href="/" class="nav-button">🏠 Inicio</a>
+				<a href="/leaderboard" class="nav-button">🏆 Leaderboard</a>
+				<a href="/shop" class="nav-button">🛒 Tienda</a>
+			</div>
+			
+			<div class="form-group">
+				<input type="email" id="username" placeholder="example@alumnos.uahurtado.cl">
+			</div>
+			<div class="form-group">
+				<input type="text" id="teamName" placeholder="Nombre del equipo nuevo">
+			</div>
+			<div class="actions">
+				<button onclick="teamAction('create', document.getElementById('teamName').value)">Crear equipo</button>
+				<button class="leave" onclick="teamAction('leave')">Salir de mi equipo</button>
+			</div>
+			
+			<div id="result"></div>
+			<div id="teams"></div>
+		</div>
+
+		<script>
+			async function teamAction(action, team) {
+				const username = document.getElementById('username').value;
+				const resultDiv = document.getElementById('result');
+				if (!username) {
+					alert('Por favor, ingresa tu email primero');
+					return;
+				}
+				const body = new URLSearchParams({ username });
+				if (team) body.append('team', team);
+				
+				const response = await fetch(`/teams/${action}`, {
+					method: 'POST',
+					headers: { 'Content-Type': 'application/x-www-form-urlencoded' },
+					body
+				});
+				if (response.ok) {
+					const data = await response.json();
+					resultDiv.innerHTML = `<div class="result success">${data.message || 'Ahora estás en ' + data.name}</div>`;
+				} else {
+					resultDiv.innerHTML = `<div class="result error">Error: ${await response.text()}</div>`;
+				}
+				loadTeams();
+			}
+			
+			async function loadTeams() {
+				const response = await fetch('/teams/list');
+				const teams = await response.json();
+				const teamsDiv = document.getElementById('teams');
+				if (teams.length === 0) {
+					teamsDiv.innerHTML = '<div class="empty-state">Todavía no hay equipos. ¡Crea el primero!</div>';
+					return;
+				}
+				teamsDiv.innerHTML = teams.map((t, i) => `
+					<div class="team ${i === 0 ? 'top' : ''}">
+						<h3>#${i + 1} ${t.name} <small>${Math.round(t.totals.score)} puntos</small></h3>
+						<small>${t.members.join(', ')} | ${t.totals.total_exp} XP | Asistencia ${t.totals.average_attendance.toFixed(0)}%</small>
+						${t.goals.map(g => `
+							<div class="goal">
+								${g.paid_at ? '✅' : '🎯'} ${g.name} (${g.coins} 🪙 c/u)
+								<div class="bar"><div style="width: ${g.progress.toFixed(0)}%"></div></div>
+							</div>
+						`).join('')}
+						<button onclick="teamAction('join', '${t.id}')" style="margin-top: 10px;">Unirme</button>
+					</div>
+				`).join('');
+			}
+			
+			loadTeams();
+		</script>
+	</body>
+	</html>
+	"#)
+}
+
+/*
+ * Simulador, "si me saco un 6.0 en el control 3 cuanto gano". Parte del ultimo scrapeo del estudiante (el cache, que trae
+ * las ponderaciones y las evaluaciones que todavia no tienen nota), le cambia lo que mande y lo pasa por
+ * apply_full_gamification sobre una copia del estudiante, asi sale lo mismo que daria el scrapeo y no se guarda nada.
+ * Como devuelve las notas pide la contraseña, que se compara con la del ultimo login bueno (sin ir a ucampus)
+ */
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct WhatIfForm {
+	username: String,
+	password: String,
+	#[serde(default)]
+	courses: Vec<WhatIfCourse>,		// Vacio devuelve los cursos como estan, para armar la pagina
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct WhatIfCourse {
+	code: String,
+	#[serde(default)]
+	grades: Vec<WhatIfGrade>,
+	attendance: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct WhatIfGrade {
+	name: String,			// Si la evaluacion existe se le cambia la nota, si no se agrega
+	grade: f32,
+	weight: Option<f32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct WhatIfSnapshot {
+	exp: u32,
+	level: u16,
+	coins: u32,
+	title: Option<String>,
+	mean: f32,
+	assist: u8,
+}
+
+impl WhatIfSnapshot {
+	fn of(student: &Student) -> Self {
+		WhatIfSnapshot {
+			exp: student.exp,
+			level: student.level,
+			coins: student.coins,
+			title: student.title.clone(),
+			mean: student.mean,
+			assist: student.assist,
+		}
+	}
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct WhatIfCourseResult {
+	code: String,
+	grades: Vec<GradeEntry>,
+	attendance: f32,
+	mean: f32,
+	awarded_exp: u32,
+	tiers: Vec<TierHit>,
+	penalties: Vec<ActivePenalty>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct WhatIfResult {
+	current: WhatIfSnapshot,
+	projected: WhatIfSnapshot,
+	new_titles: Vec<String>,
+	level_progress: LevelProgress,
+	courses: Vec<WhatIfCourseResult>,
+}
+
+fn what_if_error(message: String) -> rocket::response::status::Custom<String> {
+	rocket::response::status::Custom(rocket::http::Status::BadRequest, message)
+}
+
+// Le pone al curso las notas y la asistencia hipoteticas, con los mismos rangos que se validan en config/rules.json
+fn apply_what_if(course: &mut CourseData, change: &WhatIfCourse) -> Result<(), rocket::response::status::Custom<String>> {
+	if let Some(attendance) = change.attendance {
+		if !(0.0..=100.0).contains(&attendance) {
+			return Err(what_if_error(format!("{}: la asistencia va de 0 a 100", course.code)));
+		}
+		course.attendance = attendance;
+	}
+	for hypothetical in &change.grades {
+		if !(1.0..=7.0).contains(&hypothetical.grade) {
+			return Err(what_if_error(format!("{}: la nota de {} tiene que ir de 1.0 a 7.0", course.code, hypothetical.name)));
+		}
+		if hypothetical.weight.is_some_and(|weight| weight <= 0.0 || weight > 100.0) {
+			return Err(what_if_error(format!("{}: la ponderacion de {} tiene que ir de 0 a 100", course.code, hypothetical.name)));
+		}
+		match course.grades.iter_mut().find(|entry| entry.name == hypothetical.name) {
+			Some(entry) => {
+				entry.grade = Some(hypothetical.grade);
+				entry.weight = hypothetical.weight.or(entry.weight);
+				entry.failing = hypothetical.grade < 4.0;
+			},
+			None => course.grades.push(GradeEntry {
+				name: hypothetical.name.clone(),
+				weight: hypothetical.weight,
+				grade: Some(hypothetical.grade),
+				date: None,
+				failing: hypothetical.grade < 4.0,
+				is_exam: false,
+			}),
+		}
+	}
+	Ok(())
+}
+
+#[get("/what-if")]
+fn what_if_page() -> RawHtml<&'static str> {
+	RawHtml(r#"
+	<!DOCTYPE html>
+	<html>
+	<head>
+		<title>Simulador - UCampus EduGame</title>
+		<style>
+			body {
+				font-family: Arial, sans-serif;
+				max-width: 800px;
+				margin: 50px auto;
+				padding: 20px;
+				background: #f5f5f5;
+			}
+			.container {
+				background: white;
+				padding: 30px;
+				border-radius: 10px;
+				box-shadow: 0 2px 10px rgba(0,0,0,0.1);
+			}
+			h1 {
+				color: #333;
+				text-align: center;
+				margin-bottom: 30px;
+			}
+			.navigation {
+				text-align: center;
+				margin-bottom: 30px;
+			}
+			.nav-button {
+				display: inline-block;
+				margin: 0 10px 10px;
+				padding: 10px 20px;
+				background: #007bff;
+				color: white;
+				text-decoration: none;
+				border-radius: 5px;
+				transition: background 0.3s;
+			}
+			.nav-button:hover {
+				background: #0056b3;
+			}
+			.form-group {
+				margin-bottom: 15px;
+			}
+			input[type="email"], input[type="password"] {
+				width: 100%;
+				padding: 12px;
+				border: 1px solid #ddd;
+				border-radius: 5px;
+				box-sizing: border-box;
+				font-size: 16px;
+			}
+			input[type="number"], input[type="text"] {
+				width: 90px;
+				padding: 5px;
+				border: 1px solid #ddd;
+				border-radius: 5px;
+			}
+			button {
+				width: 100%;
+				padding: 12px;
+				background: #28a745;
+				color: white;
+				border: none;
+				border-radius: 5px;
+				cursor: pointer;
+				font-size: 16px;
+				margin-bottom: 20px;
+			}
+			.course {
+				border: 2px solid #ddd;
+				border-radius: 10px;
+				padding: 15px;
+				margin-bottom: 15px;
+			}
+			.course h3 {
+				margin: 0 0 10px 0;
+			}
+			.row {
+				display: flex;
+				justify-content: space-between;
+				align-items: center;
+				padding: 4px 0;
+				border-bottom: 1px solid #eee;
+			}
+			.row small {
+				color: #666;
+			}
+			.comparison {
+				display: grid;
+				grid-template-columns: repeat(4, 1fr);
+				gap: 10px;
+				margin: 15px 0;
+			}
+			.stat-item {
+				padding: 10px;
+				border-radius: 5px;
+				text-align: center;
+				border: 1px solid #ddd;
+			}
+			.gain {
+				color: #28a745;
+			}
+			.loss {
+				color: #dc3545;
+			}
+			.result {
+				margin-bottom: 20px;
+				padding: 15px;
+				border-radius: 5px;
+			}
+			.success {
+				background: #d4edda;
+				color: #155724;
+			}
+			.error {
+				background: #f8d7da;
+				color: #721c24;
+			}
 		</style>
-		<script>
-			function toggleAverage(element) {{
-				if (element.classList.contains('revealed')) {{
-					// If already revealed, hide it again
-					element.textContent = 'Copuchentear';
-					element.classList.remove('revealed');
-				}} else {{
-					// Reveal the actual grade
-					const actualValue = element.getAttribute('data-value');
-					element.textContent = 'Promedio: ' + actualValue;
-					element.classList.add('revealed');
-				}}
-			}}
-		</script>
 	</head>
 	<body>
 		<div class="container">
-			<h1>🏆 Leaderboard - Top Estudiantes</h1>
+			<h1>🔮 Simulador</h1>
 			
 			<div class="navigation">
 				<a href="/" class="nav-button">🏠 Inicio</a>
-				<a href="/coinflip" class="nav-button">🪙 Coin Flip Game</a>
+				<a href="/leaderboard" class="nav-button">🏆 Leaderboard</a>
 				<a href="/shop" class="nav-button">🛒 Tienda</a>
-				<a href="/slots" class="nav-button">🎰 Slots</a>
 			</div>
 			
-			<div class="leaderboard">
-				{}
+			<p>Cambia tus notas y asistencia para ver cuánta experiencia y monedas ganarías. No se guarda nada, usa los datos de tu último ingreso.</p>
+			<div class="form-group">
+				<input type="email" id="username" placeholder="example@alumnos.uahurtado.cl">
+			</div>
+			<div class="form-group">
+				<input type="password" id="password" placeholder="Contraseña de U-Campus">
 			</div>
+			<button onclick="loadCourses()">Cargar mis cursos</button>
 			
-			{}
+			<div id="result"></div>
+			<div id="courses"></div>
+			<button id="calculate" style="display: none;" onclick="calculate()">Calcular</button>
+			<div id="projection"></div>
 		</div>
+
+		<script>
+			let original = [];
+			
+			async function whatIf(courses) {
+				const username = document.getElementById('username').value;
+				const password = document.getElementById('password').value;
+				if (!username || !password) {
+					alert('Por favor, ingresa tu email y contraseña primero');
+					return null;
+				}
+				const response = await fetch('/what-if', {
+					method: 'POST',
+					headers: { 'Content-Type': 'application/json' },
+					body: JSON.stringify({ username, password, courses })
+				});
+				if (!response.ok) {
+					document.getElementById('result').innerHTML = `<div class="result error">Error: ${await response.text()}</div>`;
+					return null;
+				}
+				document.getElementById('result').innerHTML = '';
+				return response.json();
+			}
+			
+			async function loadCourses() {
+				const data = await whatIf([]);
+				if (!data) return;
+				original = data.courses;
+				document.getElementById('courses').innerHTML = original.map((c, i) => `
+					<div class="course">
+						<h3>${c.code} <small>promedio ${c.mean.toFixed(2)}</small></h3>
+						<div class="row">
+							<span>Asistencia (%)</span>
+							<input type="number" id="attendance-${i}" min="0" max="100" step="1" value="${c.attendance}">
+						</div>
+						${c.grades.map((g, j) => `
+							<div class="row">
+								<span>${g.name} <small>${g.weight !== null ? g.weight + '%' : ''}</small></span>
+								<input type="number" id="grade-${i}-${j}" min="1" max="7" step="0.1" value="${g.grade !== null ? g.grade : ''}" placeholder="sin nota">
+							</div>
+						`).join('')}
+						<div class="row">
+							<input type="text" id="new-name-${i}" placeholder="Nueva evaluación">
+							<input type="number" id="new-weight-${i}" min="1" max="100" placeholder="%">
+							<input type="number" id="new-grade-${i}" min="1" max="7" step="0.1" placeholder="nota">
+						</div>
+					</div>
+				`).join('');
+				document.getElementById('calculate').style.display = 'block';
+				document.getElementById('projection').innerHTML = '';
+			}
+			
+			// Solo se mandan los cambios, lo que no se toca queda como en el ultimo ingreso
+			function collectChanges() {
+				return original.map((c, i) => {
+					const change = { code: c.code, grades: [] };
+					const attendance = parseFloat(document.getElementById(`attendance-${i}`).value);
+					if (!isNaN(attendance) && attendance !== c.attendance) change.attendance = attendance;
+					c.grades.forEach((g, j) => {
+						const grade = parseFloat(document.getElementById(`grade-${i}-${j}`).value);
+						if (!isNaN(grade) && grade !== g.grade) change.grades.push({ name: g.name, grade });
+					});
+					const name = document.getElementById(`new-name-${i}`).value.trim();
+					const grade = parseFloat(document.getElementById(`new-grade-${i}`).value);
+					const weight = parseFloat(document.getElementById(`new-weight-${i}`).value);
+					if (name && !isNaN(grade)) change.grades.push({ name, grade, weight: isNaN(weight) ? null : weight });
+					return change;
+				}).filter(change => change.attendance !== undefined || change.grades.length > 0);
+			}
+			
+			function delta(now, then, unit) {
+				const diff = now - then;
+				if (diff === 0) return '';
+				return `<div class="${diff > 0 ? 'gain' : 'loss'}">${diff > 0 ? '+' : ''}${diff} ${unit}</div>`;
+			}
+			
+			async function calculate() {
+				const data = await whatIf(collectChanges());
+				if (!data) return;
+				const { current, projected } = data;
+				const progress = data.level_progress;
+				document.getElementById('projection').innerHTML = `
+					<div class="result success">
+						<h3>Así quedarías</h3>
+						<div class="comparison">
+							<div class="stat-item"><strong>Nivel</strong><br>${projected.level}${delta(projected.level, current.level, '')}</div>
+							<div class="stat-item"><strong>Experiencia</strong><br>${projected.exp} XP${delta(projected.exp, current.exp, 'XP')}</div>
+							<div class="stat-item"><strong>Monedas</strong><br>${projected.coins} 🪙${delta(projected.coins, current.coins, '🪙')}</div>
+							<div class="stat-item"><strong>Título</strong><br>${projected.title || '-'}</div>
+						</div>
+						${data.new_titles.map(t => `<div>🎖️ Nuevo título: ${t}</div>`).join('')}
+						<div><small>Las monedas incluyen las que da la experiencia y el premio de los títulos nuevos.</small></div>
+						<div>${progress.next_level_exp === null ? 'Nivel máximo' : `${progress.percent.toFixed(0)}% hacia el nivel ${progress.level + 1}`}</div>
+					</div>
+					${data.courses.map(c => `
+						<div class="course">
+							<h3>${c.code} <small>promedio ${c.mean.toFixed(2)}, asistencia ${c.attendance}%, ${c.awarded_exp} XP</small></h3>
+							${c.tiers.map(t => `<div class="gain">✔ ${t.metric === 'grade' ? 'Promedio' : 'Asistencia'} ≥ ${t.min}: +${t.exp} XP</div>`).join('')}
+							${c.penalties.map(p => `<div class="loss">✘ ${p.metric === 'grade' ? 'Promedio' : 'Asistencia'} ≤ ${p.at_most}: -${p.exp} XP, severidad ${p.severity}</div>`).join('')}
+						</div>
+					`).join('')}
+				`;
+			}
+		</script>
 	</body>
 	</html>
-	"#, 
-	if leaderboard_html.is_empty() {
-		r#"<div class="empty-state">No hay estudiantes registrados aún. ¡Sé el primero en unirte!</div>"#.to_string()
-	} else {
-		leaderboard_html
-	},
-	if sorted_students.is_empty() {
-		"".to_string()
-	} else {
-		format!(r#"<p style="text-align: center; margin-top: 20px; color: #666;"><small>Total de estudiantes registrados: {}</small></p>"#, sorted_students.len())
+	"#)
+}
+
+//API - Simulador, no guarda nada
+#[post("/what-if", data = "<form>")]
+async fn calculate_what_if(form: Json<WhatIfForm>, storage: &State<StudentStorage>, scrape_cache: &State<ScrapeCache>, rules: &State<XpRules>, level_system: &State<LevelSystem>, request_id: RequestId) -> Result<Json<WhatIfResult>, rocket::response::status::Custom<String>> {
+	let caches = scrape_cache.read().await;
+	let students = storage.read().await;
+	let (Some(student), Some(cached)) = (students.get(&form.username), caches.get(&form.username)) else {
+		return Err(rocket::response::status::Custom(
+			rocket::http::Status::NotFound,
+			"Estudiante no encontrado. Ingresa a la plataforma primero.".to_string()
+		));
+	};
+	if !cached.password.as_ref().is_some_and(|hash| hash.matches(&form.password)) {
+		tracing::warn!(request_id = %request_id, user = %redact_email(&form.username), "simulacion con contraseña incorrecta");
+		return Err(rocket::response::status::Custom(
+			rocket::http::Status::Unauthorized,
+			"Credenciales incorrectas, usa la misma contraseña con la que ingresaste".to_string()
+		));
+	}
+
+	let mut courses: Vec<CourseData> = cached.courses.values().map(|cached| cached.data.clone()).collect();
+	courses.sort_by(|a, b| a.code.cmp(&b.code));
+	for change in &form.courses {
+		let course = courses.iter_mut()
+			.find(|course| course.code == change.code)
+			.ok_or_else(|| what_if_error(format!("No tienes el curso {}", change.code)))?;
+		apply_what_if(course, change)?;
+	}
+
+	let mut projected = student.clone();
+	projected.apply_full_gamification(&courses, rules, level_system);
+
+	let new_titles = level_system.tiers.iter()
+		.filter(|tier| !student.level_rewards.contains_key(&tier.id) && projected.level_rewards.contains_key(&tier.id))
+		.map(|tier| tier.name.clone())
+		.collect();
+	let course_results = courses.into_iter()
+		.map(|course| {
+			let progress = projected.courses.get(&course.code).cloned().unwrap_or_default();
+			WhatIfCourseResult {
+				code: course.code,
+				grades: course.grades,
+				attendance: course.attendance,
+				mean: progress.mean,
+				awarded_exp: progress.awarded_exp.saturating_sub(progress.penalty_exp),
+				tiers: progress.tiers,
+				penalties: progress.penalties,
+			}
+		})
+		.collect();
+
+	tracing::info!(request_id = %request_id, user = %redact_email(&form.username), changes = form.courses.len(), exp = projected.exp, "simulacion");
+
+	Ok(Json(WhatIfResult {
+		current: WhatIfSnapshot::of(student),
+		level_progress: level_system.progress(projected.exp),
+		projected: WhatIfSnapshot::of(&projected),
+		new_titles,
+		courses: course_results,
 	}))
 }
 
@@ -1279,6 +2510,8 @@ fn coinflip_page() -> RawHtml<&'static str> {
 				<a href="/leaderboard" class="nav-button">🏆 Leaderboard</a>
 				<a href="/shop" class="nav-button">🛒 Tienda</a>
 				<a href="/slots" class="nav-button">🎰 Slots</a>
+				<a href="/teams" class="nav-button">👥 Equipos</a>
+				<a href="/what-if" class="nav-button">🔮 Simulador</a>
 			</div>
 			
 			<div id="balanceDiv" class="balance" style="display: none;">
@@ -1403,6 +2636,7 @@ fn coinflip_page() -> RawHtml<&'static str> {
 						<h3>Resultado: ${data.result === 'heads' ? 'Cara' : 'Sello'}</h3>
 						<p>${message}</p>
 						<p><strong>Monedas restantes: ${data.new_balance} 🪙</strong></p>
+						${data.new_achievements.map(b => `<p>🏆 Nuevo logro: ${b.icon} ${b.name}</p>`).join('')}
 					</div>
 				`;
 			}
@@ -1422,9 +2656,199 @@ async fn get_balance(username: String, storage: &State<StudentStorage>) -> Resul
 	}
 }
 
+//API - Logros del estudiante, los desbloqueados con su fecha y los que le faltan
+#[get("/achievements/<username>")]
+async fn get_achievements(username: String, storage: &State<StudentStorage>) -> Result<Json<serde_json::Value>, rocket::response::status::NotFound<String>> {
+	let students = storage.read().await;
+	match students.get(&username) {
+		Some(student) => {
+			let (unlocked, locked) = achievements::badges(&student.achievements);
+			Ok(Json(serde_json::json!({ "unlocked": unlocked, "locked": locked })))
+		},
+		None => Err(rocket::response::status::NotFound("Student not found".to_string()))
+	}
+}
+
+//API - Historial de premios por puesto, el mas reciente primero
+#[get("/rank-rewards")]
+async fn get_rank_rewards(payouts: &State<RankPayouts>) -> Json<Vec<RankPayout>> {
+	let mut history: Vec<RankPayout> = payouts.read().await.values().cloned().collect();
+	history.sort_by(|a, b| b.period.cmp(&a.period));
+	Json(history)
+}
+
+//API - Check-in diario, una vez por dia (en hora de Chile) y el premio sube con la racha
+#[post("/check-in", data = "<form>")]
+async fn check_in(form: Form<CheckInForm>, storage: &State<StudentStorage>, rules: &State<XpRules>, level_system: &State<LevelSystem>, request_id: RequestId) -> Result<Json<CheckInResult>, rocket::response::status::Custom<String>> {
+	let mut students = storage.write().await;
+	let student = students.get_mut(&form.username)
+		.ok_or_else(|| rocket::response::status::Custom(
+			rocket::http::Status::NotFound,
+			"Estudiante no encontrado. Ingresa a la plataforma primero.".to_string()
+		))?;
+
+	let outcome = student.streak.check_in(checkin::local_day(Utc::now()))
+		.map_err(|message| rocket::response::status::Custom(rocket::http::Status::Conflict, message))?;
+	let (coins, exp) = rules.check_in.reward(outcome.streak);
+	student.coins += coins;
+	student.exp += exp;
+
+	let mut message = format!("Check-in del dia, racha de {} {}", outcome.streak, if outcome.streak == 1 { "dia" } else { "dias" });
+	if outcome.freezes_used > 0 {
+		message += &format!(", se usaron {} protectores de racha", outcome.freezes_used);
+	}
+	if outcome.broken {
+		message += ", la racha anterior se perdio";
+	}
+	student.record_exp_change(EventKind::CheckIn, message.clone(), exp as i64, coins as i64, level_system);
+	let new_achievements = student.check_achievements(false);
+
+	tracing::info!(request_id = %request_id, user = %redact_email(&form.username), streak = outcome.streak, freezes_used = outcome.freezes_used, coins, exp, "check-in");
+
+	Ok(Json(CheckInResult {
+		message,
+		streak: outcome.streak,
+		longest: student.streak.longest,
+		freezes_used: outcome.freezes_used,
+		freezes_left: student.streak.freezes,
+		coins,
+		exp,
+		new_balance: student.coins,
+		level: student.level,
+		new_achievements,
+	}))
+}
+
+//API - Niveles, la experiencia minima de cada nivel con su titulo y cuanto le falta a cada estudiante para el siguiente
+#[get("/levels")]
+async fn get_levels(storage: &State<StudentStorage>, rules: &State<XpRules>, level_system: &State<LevelSystem>) -> Json<serde_json::Value> {
+	let thresholds: Vec<serde_json::Value> = (1..=level_system.max_level())
+		.map(|level| serde_json::json!({
+			"level": level,
+			"exp": level_system.exp_for_level(level),
+			"title": level_system.tier_for(level).map(|tier| tier.name.clone()),
+		}))
+		.collect();
+
+	let students = storage.read().await;
+	let progress: Vec<serde_json::Value> = leaderboard_order(&students).into_iter()
+		.map(|(_, student)| {
+			let mut entry = serde_json::to_value(level_system.progress(student.exp)).unwrap();
+			entry["name"] = serde_json::Value::String(student.name.clone()); // El correo no se publica
+			entry["title"] = serde_json::to_value(&student.title).unwrap();
+			entry
+		})
+		.collect();
+
+	Json(serde_json::json!({
+		"curve": rules.levels.curve,
+		"max_level": level_system.max_level(),
+		"thresholds": thresholds,
+		"students": progress,
+	}))
+}
+
+// Como queda cada estudiante al cerrar la temporada, en el orden del leaderboard
+fn season_standings(students: &HashMap<String, Student>) -> Vec<Standing> {
+	leaderboard_order(students).into_iter()
+		.enumerate()
+		.map(|(i, (username, student))| Standing {
+			rank: i + 1,
+			username: username.clone(),
+			name: student.name.clone(),
+			level: student.level,
+			exp: student.exp,
+			coins: student.coins,
+			mean: student.mean,
+			assist: student.assist,
+			bonus: student.bonus,
+			penalty: student.penalty,
+			achievements: achievements::badges(&student.achievements).0.into_iter().map(|badge| badge.id).collect(),
+		})
+		.collect()
+}
+
+//API - Temporada actual y las anteriores con su leaderboard final
+#[get("/seasons")]
+async fn get_seasons(seasons: &State<SeasonState>) -> Json<serde_json::Value> {
+	let seasons = seasons.read().await;
+	let (year, term) = seasons.current.catalog.term();
+	Json(serde_json::json!({
+		"current": { "id": seasons.current.id, "year": year, "term": term, "started_at": seasons.current.started_at },
+		"archives": seasons.archives.iter().rev().collect::<Vec<_>>(),
+	}))
+}
+
+#[get("/seasons/<id>")]
+async fn get_season(id: &str, seasons: &State<SeasonState>) -> Result<Json<SeasonArchive>, rocket::response::status::NotFound<String>> {
+	seasons.read().await.archive(id)
+		.map(|archive| Json(archive.clone()))
+		.ok_or_else(|| rocket::response::status::NotFound("Temporada no encontrada".to_string()))
+}
+
+//API - Como le fue al estudiante en cada temporada cerrada, la mas reciente primero
+#[get("/students/<username>/seasons")]
+async fn get_student_seasons(username: &str, seasons: &State<SeasonState>) -> Json<Vec<serde_json::Value>> {
+	let seasons = seasons.read().await;
+	let history = seasons.archives.iter().rev()
+		.filter_map(|archive| {
+			let standing = archive.standings.iter().find(|s| s.username == username)?;
+			Some(serde_json::json!({
+				"season": archive.id,
+				"started_at": archive.started_at,
+				"closed_at": archive.closed_at,
+				"players": archive.standings.len(),
+				"standing": standing,
+			}))
+		})
+		.collect();
+	Json(history)
+}
+
+/*
+ * API - Cierra la temporada actual y parte la siguiente con el catalogo que viene en el body (el mismo formato de config/catalog.json).
+ * Se archiva el leaderboard y se borran estudiantes y cache, la temporada nueva parte con otra economia
+ */
+#[post("/seasons/close", data = "<catalog>")]
+async fn close_season(_admin: Admin, catalog: Json<CourseCatalog>, seasons: &State<SeasonState>, storage: &State<StudentStorage>, scrape_cache: &State<ScrapeCache>, teams: &State<TeamStorage>, request_id: RequestId) -> Result<Json<SeasonArchive>, rocket::response::status::Custom<String>> {
+	let catalog = catalog.into_inner();
+	catalog.validate().map_err(|e| rocket::response::status::Custom(rocket::http::Status::BadRequest, e))?;
+
+	// Mismo orden de locks que el resto: temporada, cache, equipos y despues estudiantes
+	let mut seasons = seasons.write().await;
+	let mut caches = scrape_cache.write().await;
+	let mut teams = teams.write().await;
+	let mut students = storage.write().await;
+
+	let standings = season_standings(&students);
+	let archive = seasons.close(standings, catalog, Utc::now())
+		.map_err(|e| rocket::response::status::Custom(rocket::http::Status::Conflict, e))?
+		.clone();
+	students.clear();
+	caches.clear();
+	// Los equipos se mantienen pero las metas se pueden volver a cumplir en la temporada nueva
+	for lobby in teams.lobbies.values_mut() {
+		lobby.paid_goals.clear();
+	}
+
+	tracing::info!(request_id = %request_id, closed = %archive.id, next = %seasons.current.id, players = archive.standings.len(), "temporada cerrada");
+	Ok(Json(archive))
+}
+
+//API - Historial de experiencia y monedas, ej: /students/<username>/timeline?kind=purchase,game&from=2025-04-01&to=2025-04-30
+#[get("/students/<username>/timeline?<kind>&<from>&<to>")]
+async fn get_timeline(username: &str, kind: Option<&str>, from: Option<&str>, to: Option<&str>, storage: &State<StudentStorage>) -> Result<Json<Vec<TimelineEvent>>, rocket::response::status::Custom<String>> {
+	let filter = TimelineFilter::parse(kind, from, to)
+		.map_err(|e| rocket::response::status::Custom(rocket::http::Status::BadRequest, e))?;
+	let students = storage.read().await;
+	let student = students.get(username)
+		.ok_or_else(|| rocket::response::status::Custom(rocket::http::Status::NotFound, "Student not found".to_string()))?;
+	Ok(Json(student.timeline.iter().filter(|event| filter.matches(event)).cloned().collect()))
+}
+
 //API - Verificacion de usuario, posteriormente se jugará coinflip
 #[post("/play-coinflip", data = "<form>")]
-async fn play_coinflip(form: Form<CoinFlipForm>, storage: &State<StudentStorage>) -> Result<Json<CoinFlipResult>, rocket::response::status::Custom<String>> {
+async fn play_coinflip(form: Form<CoinFlipForm>, storage: &State<StudentStorage>, level_system: &State<LevelSystem>, request_id: RequestId) -> Result<Json<CoinFlipResult>, rocket::response::status::Custom<String>> {
 	let mut students = storage.write().await;
 	//lo mismo de antes, se obtiene el estudiante por su username
 
@@ -1433,6 +2857,7 @@ async fn play_coinflip(form: Form<CoinFlipForm>, storage: &State<StudentStorage>
 			rocket::http::Status::NotFound,
 			"Estudiante no encontrado. Ingresa a la palaforma primero.".to_string()
 		))?;
+	check_game_unlocked(student, "coinflip", level_system)?;
 
 	//Apuesta solo lo que tienes, no se permite apostar con deuda!
 	if student.coins < form.bet_amount {
@@ -1459,6 +2884,10 @@ async fn play_coinflip(form: Form<CoinFlipForm>, storage: &State<StudentStorage>
 
 	student.coins = new_exp;
 	//Actualizacion del estudiante
+	let coins_delta = if won { coins_won as i64 } else { -(coins_lost as i64) };
+	student.record(EventKind::Game, format!("Coinflip: {}", if won { "ganaste" } else { "perdiste" }), 0, coins_delta);
+
+	tracing::info!(request_id = %request_id, user = %redact_email(&form.username), bet = form.bet_amount, won, balance = student.coins, "coinflip");
 
 	Ok(Json(CoinFlipResult {
 		result: flip_result.to_string(),
@@ -1466,12 +2895,14 @@ async fn play_coinflip(form: Form<CoinFlipForm>, storage: &State<StudentStorage>
 		coins_won,
 		coins_lost,
 		new_balance: student.coins,
+		new_achievements: student.check_achievements(false),
 	}))
 }
 
 #[get("/shop")]
-fn shop_page() -> RawHtml<&'static str> {
-	RawHtml(r#"
+fn shop_page(rules: &State<XpRules>) -> RawHtml<String> {
+	// El protector de racha sale de config/rules.json, el resto de la pagina es fija
+	let page = r#"
 	<!DOCTYPE html>
 	<html>
 	<head>
@@ -1636,6 +3067,8 @@ fn shop_page() -> RawHtml<&'static str> {
 				<a href="/leaderboard" class="nav-button">🏆 Leaderboard</a>
 				<a href="/coinflip" class="nav-button">🪙 Coin Flip</a>
 				<a href="/slots" class="nav-button">🎰 Slots</a>
+				<a href="/teams" class="nav-button">👥 Equipos</a>
+				<a href="/what-if" class="nav-button">🔮 Simulador</a>
 			</div>
 			
 			<div class="form-group">
@@ -1684,6 +3117,24 @@ fn shop_page() -> RawHtml<&'static str> {
 						</button>
 					</div>
 				</div>
+				
+				<div class="shop-item">
+					<div class="item-header">
+						<div class="item-icon">🧊</div>
+						<div class="item-name">Protector de racha</div>
+						<div class="item-price">{freeze_price} 🪙 cada uno</div>
+					</div>
+					<div class="item-description">
+						Cubre un día sin check-in para que no se corte tu racha. Puedes guardar hasta {max_freezes}.
+					</div>
+					<div class="purchase-form">
+						<input type="number" class="quantity-input" min="1" max="{max_freezes}" value="1" 
+							   id="streak_freeze-quantity">
+						<button class="buy-btn" onclick="purchaseItem('streak_freeze')">
+							Comprar
+						</button>
+					</div>
+				</div>
 			</div>
 			
 			<div id="result"></div>
@@ -1741,6 +3192,7 @@ fn shop_page() -> RawHtml<&'static str> {
 									<p>${data.message}</p>
 									<p>Monedas gastadas: ${data.coins_spent} 🪙</p>
 									<p>Monedas restantes: ${data.new_balance} 🪙</p>
+									${data.new_achievements.map(b => `<p>🏆 Nuevo logro: ${b.icon} ${b.name}</p>`).join('')}
 								</div>
 							`;
 							document.getElementById('currentBalance').textContent = data.new_balance;
@@ -1758,13 +3210,16 @@ fn shop_page() -> RawHtml<&'static str> {
 		</script>
 	</body>
 	</html>
-	"#)
+	"#;
+	RawHtml(page
+		.replace("{freeze_price}", &rules.check_in.freeze_price.to_string())
+		.replace("{max_freezes}", &rules.check_in.max_freezes.to_string()))
 }
 
 
 // Estructura para el formulario de compra
 #[post("/purchase", data = "<form>")]
-async fn purchase_item(form: Form<PurchaseForm>, storage: &State<StudentStorage>) -> Result<Json<PurchaseResult>, rocket::response::status::Custom<String>> {
+async fn purchase_item(form: Form<PurchaseForm>, storage: &State<StudentStorage>, rules: &State<XpRules>, level_system: &State<LevelSystem>, request_id: RequestId) -> Result<Json<PurchaseResult>, rocket::response::status::Custom<String>> {
 	let mut students = storage.write().await;
 
 	// Verifica si el estudiante existe
@@ -1776,8 +3231,9 @@ async fn purchase_item(form: Form<PurchaseForm>, storage: &State<StudentStorage>
 
 	// Verifica si el tipo de item es válido y obtiene el precio, cantidad maxima
 	let (price_per_unit, max_quantity, item_name) = match form.item_type.as_str() {
-		"decimal" => (250 as u32, 10 as u32, "Décimas"), //para las decimas solo dejamos 10 de una sola compra por que si no serían muchos puntos, pero si se meten a la tienda nuevamente les deja comprar otra vez
-		"experience" => (150 as u32, 20 as u32, "Experiencia (100 XP)"),
+		"decimal" => (250_u32, 10_u32, "Décimas"), //para las decimas solo dejamos 10 de una sola compra por que si no serían muchos puntos, pero si se meten a la tienda nuevamente les deja comprar otra vez
+		"experience" => (150_u32, 20_u32, "Experiencia (100 XP)"),
+		"streak_freeze" => (rules.check_in.freeze_price, rules.check_in.max_freezes, "Protector de racha"), // Precio y maximo en config/rules.json
 		_ => return Err(rocket::response::status::Custom(
 			rocket::http::Status::BadRequest,
 			"Tipo de item inválido.".to_string()
@@ -1792,7 +3248,17 @@ async fn purchase_item(form: Form<PurchaseForm>, storage: &State<StudentStorage>
 		));
 	}
 
-	let total_cost = price_per_unit * form.quantity;
+	// Los protectores se acumulan, pero solo hasta max_freezes guardados
+	if form.item_type == "streak_freeze" && student.streak.freezes + form.quantity > rules.check_in.max_freezes {
+		return Err(rocket::response::status::Custom(
+			rocket::http::Status::BadRequest,
+			format!("Puedes tener hasta {} protectores de racha, ya tienes {}", rules.check_in.max_freezes, student.streak.freezes)
+		));
+	}
+
+	// Descuento del titulo actual
+	let discount = level_system.tier_for(student.level).map_or(0, |tier| tier.shop_discount);
+	let total_cost = price_per_unit * form.quantity * (100 - discount as u32) / 100;
 
 	//Limite de la compra en caso de no existir saldo para lo elejido
 	if student.coins < total_cost {
@@ -1803,6 +3269,8 @@ async fn purchase_item(form: Form<PurchaseForm>, storage: &State<StudentStorage>
 			new_balance: student.coins,
 			item_received: "".to_string(),
 			quantity: 0,
+			discount,
+			new_achievements: vec![],
 		}));
 	}
 
@@ -1812,31 +3280,42 @@ async fn purchase_item(form: Form<PurchaseForm>, storage: &State<StudentStorage>
 	match form.item_type.as_str() {
 		"decimal" => {
 			let decimal_boost = form.quantity as f32 * 0.1;
+			student.decimal_bonus += decimal_boost; //Se guarda aparte para que la proxima actualizacion no las borre
 			student.mean += decimal_boost;
 			student.grades += (decimal_boost * 10.0) as u16;
 		},
 		"experience" => {
 			let exp_boost = form.quantity * 100;
 			student.exp += exp_boost;
-			let level_system = LevelSystem;
-			student.level = level_system.level_for_exp(student.exp); //Actualizacion dinamica del nivel en base a los puntos
+			student.record_exp_change(EventKind::Purchase, format!("Compraste {} {}", form.quantity, item_name), exp_boost as i64, -(total_cost as i64), level_system); //Actualizacion dinamica del nivel en base a los puntos
 		},
+		"streak_freeze" => student.streak.freezes += form.quantity,
 		_ => {}
 	}
+	if form.item_type != "experience" {
+		student.record(EventKind::Purchase, format!("Compraste {} {}", form.quantity, item_name), 0, -(total_cost as i64));
+	}
+
+	tracing::info!(request_id = %request_id, user = %redact_email(&form.username), item = %form.item_type, quantity = form.quantity, spent = total_cost, balance = student.coins, "compra");
 
 	Ok(Json(PurchaseResult {
 		success: true,
-		message: format!("Has comprado {} {} exitosamente!", form.quantity, item_name),
+		message: match discount {
+			0 => format!("Has comprado {} {} exitosamente!", form.quantity, item_name),
+			_ => format!("Has comprado {} {} exitosamente! ({}% de descuento por tu titulo)", form.quantity, item_name, discount),
+		},
 		coins_spent: total_cost,
+		discount,
 		new_balance: student.coins,
 		item_received: item_name.to_string(),
 		quantity: form.quantity,
+		new_achievements: student.check_achievements(false),
 	}))
 }
 
 //API - Obtencion de items de la tienda
 #[get("/shop/items")]
-fn get_shop_items() -> Json<Vec<ShopItem>> {
+fn get_shop_items(rules: &State<XpRules>) -> Json<Vec<ShopItem>> {
 	let items = vec![
 		ShopItem {
 			id: "decimal".to_string(),
@@ -1852,13 +3331,20 @@ fn get_shop_items() -> Json<Vec<ShopItem>> {
 			price: 30,
 			max_quantity: 20,
 		},
+		ShopItem {
+			id: "streak_freeze".to_string(),
+			name: "Protector de racha".to_string(),
+			description: "Cubre un día sin check-in para no perder la racha".to_string(),
+			price: rules.check_in.freeze_price,
+			max_quantity: rules.check_in.max_freezes,
+		},
 	];
 	Json(items)
 }
 
 //API - Slots, aquí se elije si gano o perdio en la maquina tragamonedas
 #[post("/play-slots", data = "<form>")]
-async fn play_slots(form: Form<SlotsForm>, storage: &State<StudentStorage>) -> Result<Json<SlotsResult>, rocket::response::status::Custom<String>> {
+async fn play_slots(form: Form<SlotsForm>, storage: &State<StudentStorage>, level_system: &State<LevelSystem>, request_id: RequestId) -> Result<Json<SlotsResult>, rocket::response::status::Custom<String>> {
 	let mut students = storage.write().await;
 	
 	let student = students.get_mut(&form.username)
@@ -1866,6 +3352,7 @@ async fn play_slots(form: Form<SlotsForm>, storage: &State<StudentStorage>) -> R
 			rocket::http::Status::NotFound, //Otra vez manejo de errores
 			"Estudiante no encontrado. Ingresa a la plataforma primero.".to_string()
 		))?;
+	check_game_unlocked(student, "slots", level_system)?;
 
 	if student.coins < form.amount {
 		return Err(rocket::response::status::Custom(
@@ -1875,7 +3362,7 @@ async fn play_slots(form: Form<SlotsForm>, storage: &State<StudentStorage>) -> R
 	}
 
 	// Lista donde le doy nombre a los simbolos
-	let symbols = vec![
+	let symbols = [
 		"IHatePyhisics", "IDontLikeAlgebra", "ILikeCounterStrike", "IHateVisualStudio", 
 		"ILikeSublimeText", "ILikeCaffeine", "PythonIsTrash", "NobodyWillReadThisxD"
 	];
@@ -1910,7 +3397,13 @@ async fn play_slots(form: Form<SlotsForm>, storage: &State<StudentStorage>) -> R
 		student.coins - form.amount
 	};
 
+	let coins_delta = new_balance as i64 - student.coins as i64;
 	student.coins = new_balance;
+	student.record(EventKind::Game, format!("Slots: {}", win_type.as_deref().unwrap_or("sin premio")), 0, coins_delta);
+	let jackpot = symbol1 == symbol2 && symbol2 == symbol3;
+	let new_achievements = student.check_achievements(jackpot);
+
+	tracing::info!(request_id = %request_id, user = %redact_email(&form.username), bet = form.amount, won, payout, balance = new_balance, "slots");
 
 	Ok(Json(SlotsResult {
 		won,
@@ -1919,6 +3412,7 @@ async fn play_slots(form: Form<SlotsForm>, storage: &State<StudentStorage>) -> R
 		payout,
 		amount_wagered: form.amount,
 		win_type,
+		new_achievements,
 	}))
 }
 
@@ -2098,6 +3592,8 @@ fn slots_page() -> RawHtml<&'static str> {
 				<a href="/leaderboard" class="nav-button">🏆 Leaderboard</a>
 				<a href="/coinflip" class="nav-button">🪙 Coin Flip Game</a>
 				<a href="/shop" class="nav-button">🛒 Tienda</a>
+				<a href="/teams" class="nav-button">👥 Equipos</a>
+				<a href="/what-if" class="nav-button">🔮 Simulador</a>
 			</div>
 			
 			<div id="balance" style="text-align: center; font-size: 18px; margin-bottom: 20px;">
@@ -2265,6 +3761,7 @@ fn slots_page() -> RawHtml<&'static str> {
 								<h3>¡GANASTE! ${result.win_type}</h3>
 								<p>Ganaste: ${result.payout} 🪙</p>
 								<p>Nuevo balance: ${result.new_balance} 🪙</p>
+								${result.new_achievements.map(b => `<p>🏆 Nuevo logro: ${b.icon} ${b.name}</p>`).join('')}
 							</div>
 						`;
 					} else {
@@ -2273,6 +3770,7 @@ fn slots_page() -> RawHtml<&'static str> {
 								<h3>No hay suerte esta vez</h3>
 								<p>Perdiste: ${result.amount_wagered} 🪙</p>
 								<p>Nuevo balance: ${result.new_balance} 🪙</p>
+								${result.new_achievements.map(b => `<p>🏆 Nuevo logro: ${b.icon} ${b.name}</p>`).join('')}
 							</div>
 						`;
 					}
@@ -2338,29 +3836,392 @@ fn slots_page() -> RawHtml<&'static str> {
 
 //API - Formulario de login para scrapeo con los datos
 #[post("/scrape", data = "<form>")]
-async fn scrape_handler(form: Form<LoginForm>, storage: &State<StudentStorage>) -> Result<Json<serde_json::Value>, rocket::response::status::Custom<String>> {
-	match scrape_ucampus(form.username.clone(), form.password.clone()).await {
-		Ok(student_data) => {
-			let mut students = storage.write().await;
-			let is_new_user = !students.contains_key(&form.username);
-			students.insert(form.username.clone(), student_data.clone());
-			
+#[allow(clippy::too_many_arguments)] // Cada State que usa el scrapeo es un argumento, no hay como juntarlos sin otro tipo
+async fn scrape_handler(form: Form<LoginForm>, storage: &State<StudentStorage>, scrape_cache: &State<ScrapeCache>, seasons: &State<SeasonState>, rules: &State<XpRules>, level_system: &State<LevelSystem>, source: &State<Box<dyn GradeSource>>, request_id: RequestId) -> Result<Json<serde_json::Value>, rocket::response::status::Custom<String>> {
+	// Todo lo que se loguee dentro del scrapeo (ucampus.rs) queda con el id de la peticion y el correo oculto
+	let span = tracing::info_span!("scrape", request_id = %request_id, user = %redact_email(&form.username));
+	match scrape_ucampus(source.as_ref(), &form, seasons, rules, level_system, storage, scrape_cache).instrument(span).await {
+		Ok(outcome) => {
+			let ScrapeOutcome { season, student: student_data, is_new_user, failed: failed_courses, stale, new_achievements } = outcome;
+			tracing::info!(request_id = %request_id, user = %redact_email(&form.username), is_new_user, failed = failed_courses.len(), stale = stale.len(), level = student_data.level, "login exitoso");
 			let mut response = serde_json::to_value(&student_data).unwrap();
+			response["courses"] = serde_json::to_value(&student_data.courses).unwrap(); // Ya se valido la contraseña, puede ver sus notas
+			response["active_penalties"] = serde_json::to_value(&student_data.active_penalties).unwrap();
 			response["is_new_user"] = serde_json::Value::Bool(is_new_user);
+			response["failed_courses"] = serde_json::to_value(&failed_courses).unwrap(); //Los cursos que no alcanzaron a llegar, se muestran como aviso
+			response["stale_courses"] = serde_json::to_value(&stale).unwrap(); //Los que se sacaron del cache, con su antiguedad
+			response["data_age_seconds"] = serde_json::json!(stale.iter().map(|c| c.age_seconds).max().unwrap_or(0));
+			response["new_achievements"] = serde_json::to_value(&new_achievements).unwrap();
+			response["season"] = serde_json::Value::String(season);
+			response["level_progress"] = serde_json::to_value(level_system.progress(student_data.exp)).unwrap();
+			response["streak_active"] = serde_json::json!(student_data.streak.active(checkin::local_day(Utc::now()))); // La racha guardada puede estar cortada
 			
 			Ok(Json(response))
 		},
-		Err(e) => Err(rocket::response::status::Custom(
-			rocket::http::Status::InternalServerError,
-			e.to_string()
-		)),
+		Err(e) => {
+			tracing::warn!(request_id = %request_id, user = %redact_email(&form.username), error = %e, "login fallido");
+			Err(rocket::response::status::Custom(
+				e.status(), //Cada error con su status, 401 contraseña mala, 503/504 ucampus caido, etc
+				e.user_message()
+			))
+		},
 	}
 }
 
 #[launch]
 fn rocket() -> _ {
+	logging::init();
+	build_rocket(Box::new(UCampus::from_env())) // EDUGAME_UCAMPUS_URL para apuntar al mock_ucampus
+}
+
+// Todo el servidor menos la fuente de notas, asi los tests lo arman con una fuente de mentira
+fn build_rocket(source: Box<dyn GradeSource>) -> rocket::Rocket<rocket::Build> {
 	let storage: StudentStorage = Arc::new(RwLock::new(HashMap::new()));
-	rocket::build()
+	let scrape_cache: ScrapeCache = Arc::new(RwLock::new(HashMap::new()));
+	let catalog = CourseCatalog::load().expect("No se pudo cargar el catalogo de cursos"); // Sin catalogo no hay nada que scrapear, mejor caerse al iniciar
+	let seasons: SeasonState = Arc::new(RwLock::new(Seasons::new(catalog))); // La primera temporada es la del catalogo con el que se inicia
+	let rules = XpRules::load().expect("No se pudieron cargar las reglas de experiencia"); // Reglas malas = experiencia mal calculada, tambien se cae al iniciar
+	/*
+	 * El log de rocket imprime las urls completas (con el correo en /balance/<username>) y en debug hasta los formularios
+	 * con la contraseña, el RequestLogger ya loguea cada peticion. Se fuerza aunque venga ROCKET_LOG_LEVEL,
+	 * para ver mas detalle esta EDUGAME_LOG que pasa por redact
+	 */
+	let figment = rocket::Config::figment().merge(("log_level", rocket::config::LogLevel::Critical));
+	let rank_payouts: RankPayouts = Arc::new(RwLock::new(HashMap::new()));
+	let teams: TeamStorage = Arc::new(RwLock::new(Teams::default()));
+	let level_system = LevelSystem::new(&rules.levels); // Se arma una vez, la tabla no cambia mientras corre el servidor
+	let rank_job = (rules.clone(), level_system.clone(), storage.clone(), rank_payouts.clone(), teams.clone());
+	rocket::custom(figment)
+		.attach(RequestLogger)
+		.attach(rocket::fairing::AdHoc::on_liftoff("Rank rewards", |_| Box::pin(async move {
+			let (rules, level_system, storage, payouts, teams) = rank_job;
+			rocket::tokio::spawn(rank_reward_job(rules, level_system, storage, payouts, teams));
+		})))
 		.manage(storage)
-		.mount("/", routes![index, scrape_handler, coinflip_page, leaderboard, get_balance, play_coinflip, shop_page, purchase_item, get_shop_items, slots_page, play_slots])
-}
\ No newline at end of file
+		.manage(scrape_cache)
+		.manage(seasons)
+		.manage(rules)
+		.manage(level_system)
+		.manage(rank_payouts)
+		.manage(teams)
+		.manage(source)
+		.mount("/", routes![index, scrape_handler, coinflip_page, leaderboard, get_balance, get_achievements, get_rank_rewards, check_in, get_levels, get_seasons, get_season, get_student_seasons, close_season, get_timeline, teams_page, get_teams, create_team, join_team, leave_team, assign_team, what_if_page, calculate_what_if, play_coinflip, shop_page, purchase_item, get_shop_items, slots_page, play_slots])
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ucampus::ScrapedData;
+	use rocket::http::{ContentType, Status};
+	use rocket::local::asynchronous::Client;
+
+	fn grade(name: &str, weight: Option<f32>, grade: Option<f32>) -> GradeEntry {
+		GradeEntry { name: name.to_string(), weight, grade, date: None, failing: false, is_exam: false }
+	}
+
+	fn assert_close(actual: f32, expected: f32) {
+		assert!((actual - expected).abs() < 1e-4, "se esperaba {}, salio {}", expected, actual);
+	}
+
+	#[test]
+	fn course_mean_weighted_when_all_weights() {
+		let grades = [
+			grade("Control 1", Some(20.0), Some(4.0)),
+			grade("Solemne 1", Some(30.0), Some(6.0)),
+			grade("Solemne 2", Some(50.0), Some(5.0)),
+		];
+		// 4.0 * 0.2 + 6.0 * 0.3 + 5.0 * 0.5, el simple seria 5.0
+		assert_close(Student::calculate_course_mean(&grades), 5.1);
+	}
+
+	#[test]
+	fn course_mean_divides_by_graded_weight() {
+		let grades = [
+			grade("Solemne 1", Some(25.0), Some(6.0)),
+			grade("Solemne 2", Some(25.0), Some(4.0)),
+			grade("Examen", Some(50.0), None),
+		];
+		// Sin el examen se divide por 50 y no por 100, si no quedaria en 2.5
+		assert_close(Student::calculate_course_mean(&grades), 5.0);
+	}
+
+	#[test]
+	fn course_mean_simple_when_a_weight_is_missing() {
+		let grades = [
+			grade("Control 1", Some(10.0), Some(7.0)),
+			grade("Control 2", None, Some(4.0)),
+			grade("Solemne 1", Some(40.0), Some(4.0)),
+		];
+		assert_close(Student::calculate_course_mean(&grades), 5.0);
+	}
+
+	#[test]
+	fn course_mean_without_grades() {
+		assert_close(Student::calculate_course_mean(&[]), 0.0);
+		assert_close(Student::calculate_course_mean(&[grade("Examen", Some(40.0), None)]), 0.0);
+	}
+
+	#[test]
+	fn games_open_at_level_one() {
+		// Coinflip y slots existian antes de los niveles, cerrarlos le quitaria el juego al que ya jugaba
+		let rules = XpRules::load().unwrap();
+		let level_system = LevelSystem::new(&rules.levels);
+		let student = Student::new("Nuevo".to_string());
+		for game in rules::GAMES {
+			assert!(check_game_unlocked(&student, game, &level_system).is_ok(), "{} cerrado en nivel 1", game);
+		}
+	}
+
+	fn course(code: &str, grades: Vec<GradeEntry>, attendance: f32) -> CourseData {
+		CourseData { code: code.to_string(), grades, attendance, sessions: vec![] }
+	}
+
+	#[test]
+	fn small_rescrape_mints_coins_for_new_exp_only() {
+		let rules = XpRules::load().unwrap();
+		let level_system = LevelSystem::new(&rules.levels);
+		let mut student = Student::new("Demo".to_string());
+		let grades = vec![grade("Solemne 1", Some(100.0), Some(6.2))];
+		student.apply_full_gamification(&[course("CSI0165", grades.clone(), 70.0)], &rules, &level_system);
+		let (exp, coins) = (student.exp, student.coins);
+
+		// Dos clases mas: 1 XP de la formula base y 4 de mejora (2 puntos * 2.0)
+		student.apply_full_gamification(&[course("CSI0165", grades, 72.0)], &rules, &level_system);
+		assert_eq!(student.exp - exp, 5);
+		let level = level_system.level_for_exp(exp + 1) as u32;
+		let expected = (level * rules.default.coins.level_multiplier + 1 / rules.default.coins.exp_divisor) / rules.default.coins.total_exp_divisor;
+		assert_eq!(student.coins - coins, expected);
+		assert!(student.coins - coins < 5, "se acuñaron {} monedas por 5 XP", student.coins - coins);
+	}
+
+	#[test]
+	fn penalty_deducts_and_refunds_on_recovery() {
+		let rules = XpRules::load().unwrap();
+		let level_system = LevelSystem::new(&rules.levels);
+		let mut student = Student::new("Demo".to_string());
+		let grades = vec![grade("Solemne 1", Some(100.0), Some(6.2))];
+		student.apply_full_gamification(&[course("CSI0168", grades.clone(), 91.0)], &rules, &level_system);
+		let (exp, coins) = (student.exp, student.coins);
+		assert!(student.active_penalties.is_empty());
+
+		// Cae al tramo de asistencia de 65%, ya se habia premiado el curso pero igual se descuenta
+		let rule_exp = rules.default.penalty_for(Metric::Attendance, 10.0).unwrap().exp;
+		student.apply_full_gamification(&[course("CSI0168", grades.clone(), 10.0)], &rules, &level_system);
+		assert_eq!(student.exp, exp - rule_exp);
+		assert_eq!(student.penalty, 1);
+		assert_eq!(student.active_penalties.len(), 1);
+		assert_eq!(student.courses["CSI0168"].penalty_exp, rule_exp);
+
+		// Vuelve a 91%, se devuelve lo descontado y nada mas (no es mejora sobre el mejor 91%)
+		student.apply_full_gamification(&[course("CSI0168", grades, 91.0)], &rules, &level_system);
+		assert_eq!(student.exp, exp);
+		assert_eq!(student.coins, coins);
+		assert!(student.active_penalties.is_empty());
+		assert_eq!(student.courses["CSI0168"].penalty_exp, 0);
+	}
+
+	#[test]
+	fn penalty_severity_change_adjusts_difference() {
+		let rules = XpRules::load().unwrap();
+		let level_system = LevelSystem::new(&rules.levels);
+		let mut student = Student::new("Demo".to_string());
+		student.apply_full_gamification(&[course("CSI0165", vec![grade("Solemne 1", Some(100.0), Some(6.5))], 100.0)], &rules, &level_system);
+		let exp = student.exp;
+
+		let mild = rules.default.penalty_for(Metric::Grade, 4.8).unwrap().exp;
+		let severe = rules.default.penalty_for(Metric::Grade, 3.5).unwrap().exp;
+		student.apply_full_gamification(&[course("CSI0165", vec![grade("Solemne 1", Some(100.0), Some(4.8))], 100.0)], &rules, &level_system);
+		assert_eq!(student.exp, exp - mild);
+		student.apply_full_gamification(&[course("CSI0165", vec![grade("Solemne 1", Some(100.0), Some(3.5))], 100.0)], &rules, &level_system);
+		assert_eq!(student.exp, exp - severe);
+		student.apply_full_gamification(&[course("CSI0165", vec![grade("Solemne 1", Some(100.0), Some(4.8))], 100.0)], &rules, &level_system);
+		assert_eq!(student.exp, exp - mild);
+	}
+
+	#[test]
+	fn improvement_not_paid_on_first_scrape() {
+		let rules = XpRules::load().unwrap();
+		let level_system = LevelSystem::new(&rules.levels);
+		let mut student = Student::new("Demo".to_string());
+		student.apply_full_gamification(&[course("CSI0165", vec![grade("Solemne 1", Some(100.0), Some(6.0))], 90.0)], &rules, &level_system);
+		assert_eq!(student.courses["CSI0165"].improvement_exp, 0);
+	}
+
+	#[test]
+	fn improvement_not_paid_for_drop_and_recover() {
+		let rules = XpRules::load().unwrap();
+		let level_system = LevelSystem::new(&rules.levels);
+		let mut student = Student::new("Demo".to_string());
+		let scrape = |student: &mut Student, mean: f32, attendance: f32| {
+			student.apply_full_gamification(&[course("CSI0165", vec![grade("Solemne 1", Some(100.0), Some(mean))], attendance)], &rules, &level_system);
+		};
+		scrape(&mut student, 6.0, 90.0);
+		let (exp, coins) = (student.exp, student.coins);
+
+		// Baja (sin caer en un castigo) y vuelve a lo mismo, no se gana nada
+		scrape(&mut student, 5.6, 86.0);
+		scrape(&mut student, 6.0, 90.0);
+		assert_eq!((student.exp, student.coins), (exp, coins));
+		assert_eq!(student.courses["CSI0165"].improvement_exp, 0);
+
+		// Pasar el mejor si paga, solo por lo que lo pasa: 0.2 puntos de promedio y 2 de asistencia
+		scrape(&mut student, 6.2, 92.0);
+		let expected = (0.2 * rules.default.improvement.exp_per_grade_point + 2.0 * rules.default.improvement.exp_per_attendance_point).round() as u32;
+		assert_eq!(student.courses["CSI0165"].improvement_exp, expected);
+	}
+
+	// Los integrantes pasan a contar como si hubieran entrado hace mas de min_membership_hours
+	fn settle_everyone(teams: &mut Teams, rules: &TeamRules) {
+		let long_ago = Utc::now() - chrono::Duration::hours(rules.min_membership_hours as i64 + 1);
+		for lobby in teams.lobbies.values_mut() {
+			lobby.joined_at.values_mut().for_each(|joined| *joined = long_ago);
+		}
+	}
+
+	#[test]
+	fn team_goals_wait_for_membership_and_pay_once() {
+		let rules = XpRules::load().unwrap();
+		let goal = rules.teams.goals.iter().find(|goal| goal.metric == rules::TeamMetric::TotalExp).unwrap();
+		let mut students = HashMap::new();
+		for username in ["a", "b"] {
+			let mut student = Student::new(username.to_string());
+			student.exp = goal.target as u32; // Cada uno llega solo a la meta de XP, las de asistencia y puntaje no
+			students.insert(username.to_string(), student);
+		}
+		let mut teams = Teams::default();
+		teams.create("Los Cracks", "a", &rules.teams).unwrap();
+		teams.join("los-cracks", "b", &rules.teams).unwrap();
+
+		// Recien entraron, todavia no cuentan
+		settle_team_goals(&mut teams, &mut students, &rules.teams);
+		assert!(students.values().all(|student| student.coins == 100));
+
+		settle_everyone(&mut teams, &rules.teams);
+		settle_team_goals(&mut teams, &mut students, &rules.teams);
+		assert!(students.values().all(|student| student.coins == 100 + goal.coins));
+
+		// b sale, vuelve a entrar y espera de nuevo, la meta ya la cobro esta temporada
+		teams.leave("b").unwrap();
+		teams.join("los-cracks", "b", &rules.teams).unwrap();
+		settle_everyone(&mut teams, &rules.teams);
+		settle_team_goals(&mut teams, &mut students, &rules.teams);
+		assert_eq!(students["b"].coins, 100 + goal.coins);
+
+		// Ni armando un equipo nuevo
+		teams.leave("a").unwrap();
+		teams.leave("b").unwrap();
+		teams.create("Otros", "b", &rules.teams).unwrap();
+		teams.join("otros", "a", &rules.teams).unwrap();
+		settle_everyone(&mut teams, &rules.teams);
+		settle_team_goals(&mut teams, &mut students, &rules.teams);
+		assert!(students.values().all(|student| student.coins == 100 + goal.coins));
+	}
+
+	// Fuente de notas de mentira, un solo curso con un 7.0 y asistencia completa
+	struct StubSource;
+
+	#[rocket::async_trait]
+	impl GradeSource for StubSource {
+		async fn fetch_student(&self, _username: &str, password: &str, _catalog: &CourseCatalog) -> Result<ScrapedData, ScrapeError> {
+			if password != "demo1234" {
+				return Err(ScrapeError::BadCredentials);
+			}
+			Ok(ScrapedData {
+				name: Some("Demo Estudiante".to_string()),
+				courses: vec![CourseData {
+					code: "CSI0165".to_string(),
+					grades: vec![grade("Solemne 1", Some(100.0), Some(7.0))],
+					attendance: 100.0,
+					sessions: vec![],
+				}],
+				failed: vec![],
+				first_error: None,
+			})
+		}
+	}
+
+	async fn scrape(client: &Client, password: &str) -> (Status, Option<serde_json::Value>) {
+		let response = client.post("/scrape")
+			.header(ContentType::Form)
+			.body(format!("username=demo%40alumnos.uahurtado.cl&password={}", password))
+			.dispatch().await;
+		let status = response.status();
+		(status, response.into_json().await)
+	}
+
+	#[rocket::async_test]
+	async fn scrape_gives_exp_and_coins() {
+		let client = Client::tracked(build_rocket(Box::new(StubSource))).await.unwrap();
+		let (status, body) = scrape(&client, "demo1234").await;
+		assert_eq!(status, Status::Ok);
+		let body = body.unwrap();
+
+		// 100 base (7.0 y 100%) + 650 de los tramos de nota + 550 de los de asistencia, sin castigos
+		assert_eq!(body["exp"], 1300);
+		assert_eq!(body["level"], 27);
+		// 100 iniciales + (27 * 2 + 1300 / 10) * 1300 / 100 acuñadas (es la primera, la XP nueva es toda) + 200 de Aprendiz y 500 de Experto
+		assert_eq!(body["coins"], 100 + 2392 + 700);
+		assert_eq!(body["title"], "Experto");
+		assert_eq!(body["is_new_user"], true);
+	}
+
+	#[rocket::async_test]
+	async fn scrape_again_gives_nothing_new() {
+		let client = Client::tracked(build_rocket(Box::new(StubSource))).await.unwrap();
+		let (_, first) = scrape(&client, "demo1234").await;
+		let (status, second) = scrape(&client, "demo1234").await;
+		assert_eq!(status, Status::Ok);
+		let (first, second) = (first.unwrap(), second.unwrap());
+		assert_eq!(second["exp"], first["exp"]);
+		assert_eq!(second["coins"], first["coins"]);
+		assert_eq!(second["is_new_user"], false);
+	}
+
+	#[rocket::async_test]
+	async fn shop_reads_freeze_rules() {
+		let client = Client::tracked(build_rocket(Box::new(StubSource))).await.unwrap();
+		let rules = XpRules::load().unwrap();
+		let page = client.get("/shop").dispatch().await.into_string().await.unwrap();
+		assert!(page.contains(&format!("{} 🪙 cada uno", rules.check_in.freeze_price)));
+		assert!(page.contains(&format!("max=\"{}\"", rules.check_in.max_freezes)));
+		assert!(!page.contains("{freeze_price}") && !page.contains("{max_freezes}"));
+
+		let items: serde_json::Value = client.get("/shop/items").dispatch().await.into_json().await.unwrap();
+		let freeze = items.as_array().unwrap().iter().find(|item| item["id"] == "streak_freeze").unwrap();
+		assert_eq!(freeze["price"], rules.check_in.freeze_price);
+		assert_eq!(freeze["max_quantity"], rules.check_in.max_freezes);
+	}
+
+	#[rocket::async_test]
+	async fn balance_and_timeline_hide_grades() {
+		let client = Client::tracked(build_rocket(Box::new(StubSource))).await.unwrap();
+		let (_, body) = scrape(&client, "demo1234").await;
+		assert!(body.unwrap()["courses"]["CSI0165"]["grades"].is_object()); // Con contraseña si
+
+		let balance: serde_json::Value = client.get("/balance/demo@alumnos.uahurtado.cl").dispatch().await.into_json().await.unwrap();
+		assert_eq!(balance["exp"], 1300);
+		assert!(balance.get("courses").is_none());
+		assert!(balance.get("active_penalties").is_none());
+
+		let timeline = client.get("/students/demo@alumnos.uahurtado.cl/timeline").dispatch().await.into_string().await.unwrap();
+		assert!(timeline.contains("CSI0165"));
+		assert!(!timeline.contains("7.0") && !timeline.contains("100%"), "{}", timeline);
+	}
+
+	#[rocket::async_test]
+	async fn timeline_events_keep_level_after_change() {
+		let client = Client::tracked(build_rocket(Box::new(StubSource))).await.unwrap();
+		scrape(&client, "demo1234").await;
+		let timeline: serde_json::Value = client.get("/students/demo@alumnos.uahurtado.cl/timeline?kind=xp_grant").dispatch().await.into_json().await.unwrap();
+		let events = timeline.as_array().unwrap();
+		assert_eq!(events.len(), 1);
+		assert_eq!(events[0]["exp"], 1300);
+		assert_eq!(events[0]["level"], 27); // El nivel alcanzado con la experiencia del evento, no el 1 de antes
+	}
+
+	#[rocket::async_test]
+	async fn scrape_with_bad_password() {
+		let client = Client::tracked(build_rocket(Box::new(StubSource))).await.unwrap();
+		let (status, _) = scrape(&client, "otra").await;
+		assert_eq!(status, Status::Unauthorized);
+	}
+}
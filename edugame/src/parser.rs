@@ -0,0 +1,387 @@
+use rocket::serde::{Deserialize, Serialize};
+use scraper::{ElementRef, Html, Selector};
+
+/*
+ * Parser de las paginas de ucampus con scraper (lo mismo que BeautifulSoup en la version de python),
+ * antes sacabamos las notas cortando strings entre <h1 class="strong"><span class=""> y </span></h1>
+ * y cada vez que ucampus cambiaba algo del html se rompia todo
+ */
+
+// Una evaluacion de la pagina notas/alumno
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "rocket::serde")]
+pub struct GradeEntry {
+	pub name: String,			// Control 1, Solemne 2, Examen...
+	pub weight: Option<f32>,	// Ponderacion en porcentaje, 25.0 = 25%
+	pub grade: Option<f32>,		// None cuando todavia no hay nota (o es el formulario vacio del examen)
+	pub date: Option<String>,	// dd/mm/aaaa tal cual sale en ucampus
+	pub failing: bool,			// ucampus le pone la clase "wrong" a las notas rojas
+	pub is_exam: bool,
+}
+
+// Nota minima para aprobar en la escala chilena
+const PASSING_GRADE: f32 = 4.0;
+
+/*
+ * Cada evaluacion viene en una fila de la tabla con la nota dentro de h1.strong > span, el resto de la fila
+ * trae el nombre, la ponderacion (xx%) y la fecha. Si no hay filas (cambiaron la tabla) se leen las notas sueltas
+ */
+pub fn parse_grades(html: &str) -> Vec<GradeEntry> {
+	let document = Html::parse_document(html);
+	let row_selector = Selector::parse("tr").unwrap();
+	let grade_selector = Selector::parse("h1.strong > span").unwrap();
+	let cell_selector = Selector::parse("td, th").unwrap();
+
+	let mut entries = vec![];
+
+	for row in document.select(&row_selector) {
+		let Some(grade_span) = row.select(&grade_selector).next() else {
+			continue; // Filas de encabezado o de otras cosas
+		};
+
+		// El nombre es el primer texto de la primera celda que no sea la de la nota, lo que viene despues (ponderacion, fecha) va aparte
+		let name = row.select(&cell_selector)
+			.filter(|cell| cell.select(&grade_selector).next().is_none())
+			.filter_map(|cell| cell.text().map(str::trim).find(|text| !text.is_empty()).map(str::to_string))
+			.next()
+			.unwrap_or_default();
+
+		let row_text = clean_text(&row);
+		entries.push(build_entry(name, &grade_span, &row_text, entries.len() + 1));
+	}
+
+	if entries.is_empty() {
+		for (i, grade_span) in document.select(&grade_selector).enumerate() {
+			entries.push(build_entry(String::new(), &grade_span, "", i + 1));
+		}
+	}
+
+	entries
+}
+
+fn build_entry(name: String, grade_span: &ElementRef, row_text: &str, position: usize) -> GradeEntry {
+	let grade = clean_text(grade_span).replace(',', ".").parse::<f32>().ok();
+	let marked_wrong = grade_span.value().classes().any(|c| c == "wrong");
+
+	let name = if name.is_empty() { format!("Evaluación {}", position) } else { name };
+	let is_exam = name.to_lowercase().contains("examen");
+
+	GradeEntry {
+		weight: find_percentage(row_text),
+		failing: marked_wrong || grade.is_some_and(|g| g < PASSING_GRADE),
+		date: find_date(row_text),
+		grade,
+		is_exam,
+		name,
+	}
+}
+
+// Todo el texto del elemento con los espacios normalizados
+pub fn clean_text(element: &ElementRef) -> String {
+	element.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Busca el primer numero seguido de %, ej: "Ponderación 25 %" o "30,5%"
+pub fn find_percentage(text: &str) -> Option<f32> {
+	let chars: Vec<char> = text.chars().collect();
+	for (i, c) in chars.iter().enumerate() {
+		if *c != '%' {
+			continue;
+		}
+		let mut end = i;
+		while end > 0 && chars[end - 1] == ' ' {
+			end -= 1;
+		}
+		let mut start = end;
+		while start > 0 && (chars[start - 1].is_ascii_digit() || chars[start - 1] == '.' || chars[start - 1] == ',') {
+			start -= 1;
+		}
+		let number: String = chars[start..end].iter().collect::<String>().replace(',', ".");
+		if let Ok(value) = number.parse::<f32>() {
+			return Some(value);
+		}
+	}
+	None
+}
+
+// Busca una fecha dd/mm/aaaa o dd-mm-aaaa
+pub fn find_date(text: &str) -> Option<String> {
+	let bytes = text.as_bytes();
+	if bytes.len() < 10 {
+		return None;
+	}
+	for start in 0..=bytes.len() - 10 {
+		let candidate = &bytes[start..start + 10];
+		let digits_ok = [0, 1, 3, 4, 6, 7, 8, 9].iter().all(|&i| candidate[i].is_ascii_digit());
+		let separator = candidate[2];
+		if digits_ok && (separator == b'/' || separator == b'-') && candidate[5] == separator {
+			return Some(String::from_utf8_lossy(candidate).to_string());
+		}
+	}
+	None
+}
+
+// Estado de una clase en asistencias2
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(crate = "rocket::serde")]
+pub enum AttendanceStatus {
+	Present,
+	Absent,
+	Justified,
+}
+
+// Una clase de la pagina asistencias2
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(crate = "rocket::serde")]
+pub struct ClassSession {
+	pub date: Option<String>,
+	pub status: AttendanceStatus,
+	pub block: Option<String>,	// Bloque o modulo de la clase, ej: "Bloque 3" o "10:15 - 11:35"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(crate = "rocket::serde")]
+pub struct AttendanceRecord {
+	pub sessions: Vec<ClassSession>,
+	pub percentage: f32,
+}
+
+/*
+ * Antes se tomaban los ultimos 3 caracteres antes de %</h1> y se le quitaba el >, con 100% y 00% pasaban cosas raras.
+ * Ahora se lee cada fila de la tabla como una clase y el porcentaje sale del h1 que viene despues del <th>Asistencia,
+ * si ese resumen no esta se calcula con las clases (las justificadas cuentan como asistidas)
+ */
+pub fn parse_attendance(html: &str) -> AttendanceRecord {
+	let document = Html::parse_document(html);
+	let row_selector = Selector::parse("tr").unwrap();
+
+	let mut sessions = vec![];
+	for row in document.select(&row_selector) {
+		let row_text = clean_text(&row);
+		let Some(date) = find_date(&row_text) else {
+			continue; // Sin fecha no es una clase, son los encabezados o el resumen
+		};
+		let Some(status) = session_status(&row, &row_text) else {
+			continue;
+		};
+		sessions.push(ClassSession {
+			date: Some(date),
+			status,
+			block: find_block(&row_text),
+		});
+	}
+
+	let percentage = summary_percentage(&document).unwrap_or_else(|| {
+		if sessions.is_empty() {
+			0.0
+		} else {
+			let attended = sessions.iter().filter(|s| s.status != AttendanceStatus::Absent).count();
+			attended as f32 * 100.0 / sessions.len() as f32
+		}
+	});
+
+	AttendanceRecord { sessions, percentage }
+}
+
+// El estado sale del texto de la fila o de las clases css que usa ucampus (ok / wrong)
+fn session_status(row: &ElementRef, row_text: &str) -> Option<AttendanceStatus> {
+	let text = row_text.to_lowercase();
+	// Primero las negaciones, "Ausente (no justificada)" e "Injustificada" tambien dicen justific
+	if ["no justific", "injustific", "sin justific"].iter().any(|negated| text.contains(negated)) {
+		return Some(AttendanceStatus::Absent);
+	}
+	if text.contains("justific") {
+		return Some(AttendanceStatus::Justified);
+	}
+	if text.contains("ausente") || text.contains("inasist") || text.contains("no asist") {
+		return Some(AttendanceStatus::Absent);
+	}
+	if text.contains("presente") || text.contains("asisti") {
+		return Some(AttendanceStatus::Present);
+	}
+
+	let any_selector = Selector::parse("*").unwrap();
+	for element in row.select(&any_selector) {
+		for class in element.value().classes() {
+			match class {
+				"wrong" | "ausente" => return Some(AttendanceStatus::Absent),
+				"ok" | "presente" => return Some(AttendanceStatus::Present),
+				_ => {}
+			}
+		}
+	}
+	None
+}
+
+// "Bloque 3", "Módulo 2" o un horario "10:15 - 11:35"
+fn find_block(text: &str) -> Option<String> {
+	let words: Vec<&str> = text.split_whitespace().collect();
+	for (i, word) in words.iter().enumerate() {
+		let lower = word.to_lowercase();
+		if (lower == "bloque" || lower == "módulo" || lower == "modulo") && i + 1 < words.len() {
+			return Some(format!("{} {}", word, words[i + 1]));
+		}
+	}
+	for (i, word) in words.iter().enumerate() {
+		if is_time(word) {
+			if i + 2 < words.len() && words[i + 1] == "-" && is_time(words[i + 2]) {
+				return Some(format!("{} - {}", word, words[i + 2]));
+			}
+			return Some(word.to_string());
+		}
+	}
+	None
+}
+
+fn is_time(word: &str) -> bool {
+	let bytes = word.as_bytes();
+	bytes.len() == 5 && bytes[2] == b':' && [0, 1, 3, 4].iter().all(|&i| bytes[i].is_ascii_digit())
+}
+
+// El h1 que viene justo despues del <th>Asistencia, igual que el find_next de la version en python
+fn summary_percentage(document: &Html) -> Option<f32> {
+	let mut after_header = false;
+	for node in document.root_element().descendants() {
+		let Some(element) = ElementRef::wrap(node) else {
+			continue;
+		};
+		match element.value().name() {
+			"th" if clean_text(&element).contains("Asistencia") => after_header = true,
+			"h1" if after_header => return find_percentage(&clean_text(&element)),
+			_ => {}
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Las mismas paginas que entrega el mock_ucampus
+	const ALGEBRA_NOTAS: &str = include_str!("../fixtures/ucampus/demo/CSI0165/notas.html");
+	const HABILIDADES_NOTAS: &str = include_str!("../fixtures/ucampus/demo/CSI0169/notas.html");
+	const ALGEBRA_ASISTENCIA: &str = include_str!("../fixtures/ucampus/demo/CSI0165/asistencias2.html");
+	const ELECTIVO_ASISTENCIA: &str = include_str!("../fixtures/ucampus/demo/CSI0168/asistencias2.html");
+	const HABILIDADES_ASISTENCIA: &str = include_str!("../fixtures/ucampus/demo/CSI0169/asistencias2.html");
+
+	// La misma pagina sin la tabla de resumen, para que el porcentaje salga de las clases
+	fn without_summary(html: &str) -> String {
+		let start = html.find("<table class=\"resumen\">").unwrap();
+		let end = start + html[start..].find("</table>").unwrap() + "</table>".len();
+		format!("{}{}", &html[..start], &html[end..])
+	}
+
+	#[test]
+	fn parse_grades_reads_each_row() {
+		let entries = parse_grades(ALGEBRA_NOTAS);
+		let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+		assert_eq!(names, ["Solemne 1", "Solemne 2", "Controles", "Examen"]);
+
+		assert_eq!(entries[0].grade, Some(4.2));
+		assert_eq!(entries[0].weight, Some(30.0));
+		assert_eq!(entries[0].date.as_deref(), Some("07/04/2025"));
+		assert!(!entries[0].failing);
+		assert_eq!(entries[2].weight, Some(40.0));
+	}
+
+	#[test]
+	fn parse_grades_exam_without_grade_or_weight() {
+		// El examen viene con el formulario vacio y sin "Ponderación xx%"
+		let exam = parse_grades(ALGEBRA_NOTAS).pop().unwrap();
+		assert_eq!(exam.name, "Examen");
+		assert!(exam.is_exam);
+		assert_eq!(exam.grade, None);
+		assert_eq!(exam.weight, None);
+		assert_eq!(exam.date, None);
+		assert!(!exam.failing);
+	}
+
+	#[test]
+	fn parse_grades_marks_failing_grades() {
+		let entries = parse_grades(HABILIDADES_NOTAS);
+		assert_eq!(entries.len(), 5);
+		let debate = entries.iter().find(|e| e.name == "Debate").unwrap();
+		assert_eq!(debate.grade, Some(3.8));
+		assert!(debate.failing);
+		assert!(entries.iter().filter(|e| e.name != "Debate").all(|e| !e.failing));
+	}
+
+	#[test]
+	fn parse_grades_without_table_uses_loose_grades() {
+		let html = r#"<div><h1 class="strong"><span class="">6,5</span></h1><h1 class="strong"><span class="wrong">3,0</span></h1></div>"#;
+		let entries = parse_grades(html);
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].name, "Evaluación 1");
+		assert_eq!(entries[0].grade, Some(6.5));
+		assert_eq!(entries[1].name, "Evaluación 2");
+		assert!(entries[1].failing);
+		assert_eq!(entries[1].weight, None);
+	}
+
+	#[test]
+	fn find_percentage_formats() {
+		assert_eq!(find_percentage("Ponderación 25 %"), Some(25.0));
+		assert_eq!(find_percentage("Controles 30,5%"), Some(30.5));
+		assert_eq!(find_percentage("Asistencia 100%"), Some(100.0));
+		assert_eq!(find_percentage("sin % de ponderacion"), None);
+		assert_eq!(find_percentage("Examen"), None);
+	}
+
+	#[test]
+	fn find_date_formats() {
+		assert_eq!(find_date("Solemne 1 07/04/2025 4,2").as_deref(), Some("07/04/2025"));
+		assert_eq!(find_date("07-04-2025").as_deref(), Some("07-04-2025"));
+		assert_eq!(find_date("7/4/2025"), None);
+		assert_eq!(find_date("07/04-2025"), None);
+		assert_eq!(find_date("corto"), None);
+	}
+
+	#[test]
+	fn parse_attendance_reads_sessions() {
+		let record = parse_attendance(ALGEBRA_ASISTENCIA);
+		assert_eq!(record.sessions.len(), 10);
+		assert_eq!(record.sessions.iter().filter(|s| s.status == AttendanceStatus::Absent).count(), 3);
+		assert_eq!(record.sessions[0].date.as_deref(), Some("10/03/2025"));
+		assert_eq!(record.sessions[0].block.as_deref(), Some("08:30 - 10:00"));
+		assert_eq!(record.percentage, 70.0);
+	}
+
+	#[test]
+	fn parse_attendance_prefers_summary_over_sessions() {
+		// 10 de 11 clases da 90.9, pero ucampus dice 91% en el resumen y ese es el que vale
+		let record = parse_attendance(ELECTIVO_ASISTENCIA);
+		assert_eq!(record.sessions.len(), 11);
+		assert_eq!(record.sessions[0].block.as_deref(), Some("Bloque 2"));
+		assert_eq!(record.percentage, 91.0);
+
+		let computed = parse_attendance(&without_summary(ELECTIVO_ASISTENCIA));
+		assert_eq!(computed.sessions.len(), 11);
+		assert!((computed.percentage - 1000.0 / 11.0).abs() < 0.001);
+	}
+
+	#[test]
+	fn parse_attendance_counts_justified_as_attended() {
+		let record = parse_attendance(&without_summary(HABILIDADES_ASISTENCIA));
+		assert_eq!(record.sessions.iter().filter(|s| s.status == AttendanceStatus::Justified).count(), 1);
+		assert_eq!(record.sessions.iter().filter(|s| s.status == AttendanceStatus::Absent).count(), 1);
+		assert_eq!(record.percentage, 90.0);
+	}
+
+	#[test]
+	fn parse_attendance_unjustified_is_absent() {
+		let html = r#"<table>
+			<tr><td>10/03/2025</td><td>Ausente (no justificada)</td></tr>
+			<tr><td>17/03/2025</td><td>Injustificada</td></tr>
+			<tr><td>24/03/2025</td><td>Inasistencia sin justificar</td></tr>
+			<tr><td>31/03/2025</td><td>Justificada</td></tr>
+		</table>"#;
+		let statuses: Vec<AttendanceStatus> = parse_attendance(html).sessions.into_iter().map(|s| s.status).collect();
+		assert_eq!(statuses, [AttendanceStatus::Absent, AttendanceStatus::Absent, AttendanceStatus::Absent, AttendanceStatus::Justified]);
+	}
+
+	#[test]
+	fn parse_attendance_empty_page() {
+		assert_eq!(parse_attendance("<html><body><p>Sin clases</p></body></html>"), AttendanceRecord::default());
+	}
+}
@@ -0,0 +1,519 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::{Deserialize, Serialize, json::serde_json};
+use std::collections::HashMap;
+
+/*
+ * Reglas de experiencia, antes eran numeros sueltos dentro de apply_course_gamification (5.5/6.0/6.5, 80/85/90, los 125 de castigo...)
+ * Ahora estan en config/rules.json, con una regla por defecto y la opcion de que un curso tenga las suyas,
+ * asi los profes pueden ajustar el juego de su curso sin recompilar
+ */
+
+pub const DEFAULT_RULES_PATH: &str = "config/rules.json";
+// Versiones del archivo que este codigo sabe leer, la 2 cambio los castigos planos (below) por tramos con severidad (at_most)
+const SUPPORTED_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum Metric {
+	Grade,		// Promedio del curso, de 1.0 a 7.0
+	Attendance,	// Porcentaje de asistencia, de 0 a 100
+}
+
+impl Metric {
+	pub fn label(&self) -> &'static str {
+		match self {
+			Metric::Grade => "promedio",
+			Metric::Attendance => "asistencia",
+		}
+	}
+}
+
+// Si el promedio/asistencia es mayor o igual a min se suma exp y bonus, se aplican todos los tramos que se cumplan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Tier {
+	pub min: f32,
+	pub exp: u32,
+	pub bonus: u8,
+}
+
+/*
+ * Castigos por tramos como el calcular_penalty de local_code.rs (<= 4.0 severidad 3, <= 5.0 severidad 2, <= 6.0 severidad 1).
+ * Si el promedio/asistencia es menor o igual a at_most el tramo aplica, y de cada metrica se toma solo el tramo mas severo.
+ * Cuando el curso vuelve a pasar el tramo el castigo se levanta solo y se devuelve la XP. Los limites tienen que quedar bajo el primer tramo
+ * de bonus de la metrica, con el <= 6.0 del prototipo un 5.8 ganaba el tramo de 5.5 y perdia XP por castigo al mismo tiempo
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PenaltyRule {
+	pub metric: Metric,
+	pub at_most: f32,
+	pub severity: u8,	// Lo que se suma al contador de castigos
+	pub exp: u32,		// Lo que se descuenta de la experiencia del curso
+}
+
+/*
+ * monedas += ((nivel * level_multiplier) + (exp ganada / exp_divisor)) * exp ganada / total_exp_divisor
+ * es la formula de siempre con los numeros afuera, pero multiplicando por la exp ganada y no por el total del estudiante,
+ * con el total cada scrapeo que subia un poco acuñaba monedas por todo el saldo
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CoinFormula {
+	pub level_multiplier: u32,
+	pub exp_divisor: u32,
+	pub total_exp_divisor: u32,
+}
+
+/*
+ * Mejora entre scrapeos: si el promedio o la asistencia de un curso sube se paga experiencia por lo que subio.
+ * Para que no se pueda bajar a proposito y volver a subir, solo se paga lo que pase del mejor valor que ya tuvo el curso
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ImprovementRule {
+	pub exp_per_grade_point: f32,		// XP por cada punto de promedio sobre el mejor anterior, 100 = 10 XP por decima
+	pub exp_per_attendance_point: f32,	// XP por cada punto de asistencia (1%) sobre la mejor anterior
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RuleSet {
+	pub base_grade_exp: f32,		// Lo maximo que da el promedio en la formula base (con un 7.0)
+	pub base_attendance_exp: f32,	// Lo maximo que da la asistencia en la formula base (con 100%)
+	pub grade_tiers: Vec<Tier>,
+	pub attendance_tiers: Vec<Tier>,
+	pub penalties: Vec<PenaltyRule>,
+	pub coins: CoinFormula,
+	#[serde(default)]
+	pub improvement: ImprovementRule,	// Sin esto en el archivo no hay experiencia por mejora
+}
+
+/*
+ * Premio por puesto, el calcular_bonus_puesto de local_code.rs: los primeros del leaderboard reciben 50 * asistencia.
+ * Se paga una vez por periodo con el orden del leaderboard al cerrar el periodo, coins y exp se multiplican por la asistencia (0 a 1).
+ * Ojo: los pagos (RankPayouts) viven en memoria igual que los estudiantes y las temporadas. Al reiniciar se pierde el historial
+ * de /rank-rewards y el periodo en curso se paga con el leaderboard que se arme despues del reinicio. No se paga dos veces
+ * porque la tarea parte desde el periodo actual, pero si se reinicia justo en el cambio de periodo el que termino no se paga
+ */
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum RewardPeriod {
+	Hourly,
+	Daily,
+	Weekly,
+}
+
+impl RewardPeriod {
+	// Identificador del periodo en el que cae la fecha, ej: 2025-W14 para semanal
+	pub fn key(&self, at: DateTime<Utc>) -> String {
+		match self {
+			RewardPeriod::Hourly => at.format("%Y-%m-%dT%H").to_string(),
+			RewardPeriod::Daily => at.format("%Y-%m-%d").to_string(),
+			RewardPeriod::Weekly => at.format("%G-W%V").to_string(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RankRewards {
+	pub top: usize,					// Cuantos puestos reciben premio
+	pub period: RewardPeriod,
+	pub coins: u32,					// Con 100% de asistencia
+	pub exp: u32,					// Con 100% de asistencia
+	pub check_interval_seconds: u64,	// Cada cuanto se revisa si el periodo cambio
+}
+
+impl Default for RankRewards {
+	fn default() -> Self {
+		RankRewards {
+			top: 5,
+			period: RewardPeriod::Weekly,
+			coins: 50,
+			exp: 50,
+			check_interval_seconds: 60,
+		}
+	}
+}
+
+/*
+ * Equipos (el Lobby de local_code3.rs). Puntaje del equipo = suma de (exp * exp_weight + asistencia * attendance_weight) de cada integrante.
+ * Las metas pagan monedas a los integrantes que llevan min_membership_hours en el equipo, y cada estudiante cobra
+ * cada meta una sola vez por temporada, aunque se cambie de equipo o salga y vuelva a entrar
+ */
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum TeamMetric {
+	TotalExp,			// Suma de la experiencia de los integrantes
+	AverageAttendance,	// Promedio de asistencia de los integrantes
+	Score,				// Puntaje del equipo
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TeamGoal {
+	pub id: String,
+	pub name: String,
+	pub metric: TeamMetric,
+	pub target: f32,
+	pub coins: u32,		// Para cada integrante
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TeamRules {
+	pub max_members: usize,
+	#[serde(default = "default_min_membership_hours")]
+	pub min_membership_hours: u32,	// Horas en el equipo antes de sumar y cobrar metas
+	pub exp_weight: f32,
+	pub attendance_weight: f32,
+	#[serde(default)]
+	pub goals: Vec<TeamGoal>,
+}
+
+fn default_min_membership_hours() -> u32 {
+	24
+}
+
+impl Default for TeamRules {
+	fn default() -> Self {
+		TeamRules {
+			max_members: 5,
+			min_membership_hours: default_min_membership_hours(),
+			exp_weight: 1.0,
+			attendance_weight: 10.0,
+			goals: vec![],
+		}
+	}
+}
+
+/*
+ * Titulos de nivel (Novato, Aprendiz...). Se alcanzan al llegar a min_level y sus premios (monedas) se pagan una sola vez,
+ * el descuento en la tienda vale mientras se tenga el titulo y los juegos de unlocks quedan cerrados hasta alcanzarlo.
+ * Ojo con cerrar un juego que ya estaba abierto (coinflip y slots existian antes de los niveles), el que ya jugaba lo pierde
+ */
+pub const GAMES: &[&str] = &["coinflip", "slots"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LevelTier {
+	pub id: String,
+	pub name: String,
+	pub min_level: u16,
+	#[serde(default)]
+	pub coins: u32,
+	#[serde(default)]
+	pub shop_discount: u8,		// Porcentaje
+	#[serde(default)]
+	pub unlocks: Vec<String>,	// Juegos de GAMES
+}
+
+/*
+ * Curva de niveles, cuanta experiencia se necesita para cada nivel.
+ * sqrt es la formula de siempre de main.rs, cubic la tabla acumulada de local_code2.rs (1^3 + 2^3 + ...),
+ * linear cada nivel cuesta lo mismo y table es una lista escrita a mano
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde", tag = "type", rename_all = "snake_case")]
+pub enum LevelCurve {
+	Sqrt,
+	Cubic,
+	Linear { exp_per_level: u32 },
+	Table { exp: Vec<u32> },	// Experiencia para llegar al nivel 2, 3, 4...
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct LevelRules {
+	pub curve: LevelCurve,
+	pub max_level: u16,
+	pub tiers: Vec<LevelTier>,	// De menor a mayor nivel
+}
+
+impl Default for LevelRules {
+	fn default() -> Self {
+		LevelRules {
+			curve: LevelCurve::Sqrt,
+			max_level: 100,
+			tiers: vec![],
+		}
+	}
+}
+
+/*
+ * Check-in diario, el premio sube con la racha: base + por_dia * (dias de racha - 1), hasta max_streak_days.
+ * Con los valores por defecto el dia 1 da 10 monedas y 20 XP, y del dia 7 en adelante 40 monedas y 80 XP.
+ * Los protectores de racha se venden en la tienda a freeze_price y se pueden tener hasta max_freezes guardados
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CheckInRules {
+	pub base_coins: u32,
+	pub coins_per_day: u32,
+	pub base_exp: u32,
+	pub exp_per_day: u32,
+	pub max_streak_days: u32,	// Desde aqui el premio deja de subir
+	#[serde(default = "default_freeze_price")]
+	pub freeze_price: u32,
+	#[serde(default = "default_max_freezes")]
+	pub max_freezes: u32,
+}
+
+fn default_freeze_price() -> u32 {
+	100
+}
+
+fn default_max_freezes() -> u32 {
+	3
+}
+
+impl Default for CheckInRules {
+	fn default() -> Self {
+		CheckInRules {
+			base_coins: 10,
+			coins_per_day: 5,
+			base_exp: 20,
+			exp_per_day: 10,
+			max_streak_days: 7,
+			freeze_price: default_freeze_price(),
+			max_freezes: default_max_freezes(),
+		}
+	}
+}
+
+impl CheckInRules {
+	// (monedas, exp) por marcar con esta racha
+	pub fn reward(&self, streak: u32) -> (u32, u32) {
+		let days = streak.clamp(1, self.max_streak_days) - 1;
+		(self.base_coins + self.coins_per_day * days, self.base_exp + self.exp_per_day * days)
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct XpRules {
+	pub version: u32,
+	pub default: RuleSet,
+	#[serde(default)]
+	pub courses: HashMap<String, RuleSet>,	// codigo del curso -> reglas propias
+	#[serde(default)]
+	pub rank_rewards: RankRewards,
+	#[serde(default)]
+	pub teams: TeamRules,
+	#[serde(default)]
+	pub levels: LevelRules,
+	#[serde(default)]
+	pub check_in: CheckInRules,
+}
+
+impl XpRules {
+	// Lee las reglas, la ruta se puede cambiar con EDUGAME_RULES
+	pub fn load() -> Result<Self, String> {
+		let path = std::env::var("EDUGAME_RULES").unwrap_or(DEFAULT_RULES_PATH.to_string());
+		let raw = std::fs::read_to_string(&path)
+			.map_err(|e| format!("No se pudieron leer las reglas {}: {}", path, e))?;
+		let rules: XpRules = serde_json::from_str(&raw)
+			.map_err(|e| format!("Reglas {} mal formadas: {}", path, e))?;
+		rules.validate()?;
+		Ok(rules)
+	}
+
+	pub fn for_course(&self, code: &str) -> &RuleSet {
+		self.courses.get(code).unwrap_or(&self.default)
+	}
+
+	fn validate(&self) -> Result<(), String> {
+		if self.version != SUPPORTED_VERSION {
+			return Err(format!("Version de reglas {} no soportada, se esperaba {}", self.version, SUPPORTED_VERSION));
+		}
+		self.default.validate("default")?;
+		for (code, rules) in &self.courses {
+			rules.validate(code)?;
+		}
+		if self.rank_rewards.top == 0 || self.rank_rewards.check_interval_seconds == 0 {
+			return Err("rank_rewards necesita top y check_interval_seconds mayores a 0".to_string());
+		}
+		if self.teams.max_members == 0 {
+			return Err("teams.max_members tiene que ser mayor a 0".to_string());
+		}
+		let mut goal_ids = std::collections::HashSet::new();
+		for goal in &self.teams.goals {
+			if !goal_ids.insert(goal.id.as_str()) {
+				return Err(format!("La meta de equipo {} esta repetida", goal.id));
+			}
+			if goal.target <= 0.0 {
+				return Err(format!("La meta de equipo {} tiene que tener un objetivo mayor a 0", goal.id));
+			}
+		}
+		if self.check_in.max_streak_days == 0 {
+			return Err("check_in.max_streak_days tiene que ser mayor a 0".to_string());
+		}
+		if self.check_in.freeze_price == 0 {
+			return Err("check_in.freeze_price tiene que ser mayor a 0".to_string());
+		}
+		let levels = &self.levels;
+		if levels.max_level < 2 || levels.max_level > 1000 {
+			return Err("levels.max_level tiene que estar entre 2 y 1000".to_string());
+		}
+		match &levels.curve {
+			LevelCurve::Linear { exp_per_level: 0 } => return Err("levels.curve.exp_per_level tiene que ser mayor a 0".to_string()),
+			LevelCurve::Table { exp } if exp.is_empty() || exp[0] == 0 || exp.windows(2).any(|pair| pair[0] >= pair[1]) => {
+				return Err("levels.curve.exp tiene que ir de menor a mayor y partir sobre 0".to_string());
+			},
+			// exp[0] es el nivel 2, con menos niveles la tabla se acaba antes y los titulos de arriba no se alcanzan nunca
+			LevelCurve::Table { exp } if exp.len() + 1 < levels.max_level as usize => {
+				return Err(format!("levels.curve.exp tiene {} niveles y levels.max_level pide {}", exp.len() + 1, levels.max_level));
+			},
+			_ => {},
+		}
+		let tiers = &levels.tiers;
+		for (i, tier) in tiers.iter().enumerate() {
+			if tiers[..i].iter().any(|other| other.id == tier.id) {
+				return Err(format!("El titulo {} esta repetido", tier.id));
+			}
+			if tier.min_level == 0 || (i > 0 && tiers[i - 1].min_level >= tier.min_level) {
+				return Err("levels.tiers tiene que ir ordenado por min_level, desde el nivel 1".to_string());
+			}
+			if tier.min_level > levels.max_level {
+				return Err(format!("El titulo {} pide un nivel sobre levels.max_level", tier.id));
+			}
+			if tier.shop_discount > 100 {
+				return Err(format!("El titulo {} tiene un descuento sobre 100%", tier.id));
+			}
+			if let Some(game) = tier.unlocks.iter().find(|game| !GAMES.contains(&game.as_str())) {
+				return Err(format!("El titulo {} desbloquea un juego que no existe: {}", tier.id, game));
+			}
+		}
+		Ok(())
+	}
+}
+
+impl RuleSet {
+	// El tramo mas severo que cae el valor de la metrica, None si esta sobre todos
+	pub fn penalty_for(&self, metric: Metric, value: f32) -> Option<&PenaltyRule> {
+		self.penalties.iter()
+			.filter(|rule| rule.metric == metric && value <= rule.at_most)
+			.max_by_key(|rule| (rule.severity, rule.exp))
+	}
+
+	fn validate(&self, name: &str) -> Result<(), String> {
+		if self.base_grade_exp < 0.0 || self.base_attendance_exp < 0.0 {
+			return Err(format!("[{}] la experiencia base no puede ser negativa", name));
+		}
+		validate_tiers(name, "grade_tiers", &self.grade_tiers, Metric::Grade)?;
+		validate_tiers(name, "attendance_tiers", &self.attendance_tiers, Metric::Attendance)?;
+		for rule in &self.penalties {
+			if !in_range(rule.metric, rule.at_most) {
+				return Err(format!("[{}] castigo con limite fuera de rango: {}", name, rule.at_most));
+			}
+			if rule.severity == 0 {
+				return Err(format!("[{}] el castigo con limite {} tiene severidad 0", name, rule.at_most));
+			}
+			let tiers = match rule.metric {
+				Metric::Grade => &self.grade_tiers,
+				Metric::Attendance => &self.attendance_tiers,
+			};
+			if let Some(tier) = tiers.first() && rule.at_most >= tier.min {
+				return Err(format!("[{}] el castigo de {} hasta {} se cruza con el tramo de bonus desde {}", name, rule.metric.label(), rule.at_most, tier.min));
+			}
+		}
+		if self.improvement.exp_per_grade_point < 0.0 || self.improvement.exp_per_attendance_point < 0.0 {
+			return Err(format!("[{}] la experiencia por mejora no puede ser negativa", name));
+		}
+		if self.coins.exp_divisor == 0 || self.coins.total_exp_divisor == 0 {
+			return Err(format!("[{}] los divisores de la formula de monedas no pueden ser 0", name));
+		}
+		Ok(())
+	}
+}
+
+// Los tramos tienen que ir de menor a mayor y dentro del rango de la metrica
+fn validate_tiers(name: &str, field: &str, tiers: &[Tier], metric: Metric) -> Result<(), String> {
+	for (i, tier) in tiers.iter().enumerate() {
+		if !in_range(metric, tier.min) {
+			return Err(format!("[{}] {} tiene un tramo fuera de rango: {}", name, field, tier.min));
+		}
+		if i > 0 && tiers[i - 1].min >= tier.min {
+			return Err(format!("[{}] {} tiene que estar ordenado de menor a mayor", name, field));
+		}
+	}
+	Ok(())
+}
+
+fn in_range(metric: Metric, value: f32) -> bool {
+	match metric {
+		Metric::Grade => (1.0..=7.0).contains(&value),
+		Metric::Attendance => (0.0..=100.0).contains(&value),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn default_rules() -> RuleSet {
+		let rules: XpRules = serde_json::from_str(include_str!("../config/rules.json")).unwrap();
+		rules.validate().unwrap();
+		rules.default
+	}
+
+	#[test]
+	fn penalty_for_takes_most_severe_band() {
+		let rules = default_rules();
+		// 3.5 cae en los tres tramos de nota, se queda con el de severidad 3
+		let rule = rules.penalty_for(Metric::Grade, 3.5).unwrap();
+		assert_eq!((rule.at_most, rule.severity, rule.exp), (4.0, 3, 75));
+		let rule = rules.penalty_for(Metric::Grade, 4.3).unwrap();
+		assert_eq!((rule.at_most, rule.severity), (4.5, 2));
+		// El limite es inclusivo
+		let rule = rules.penalty_for(Metric::Grade, 5.0).unwrap();
+		assert_eq!((rule.at_most, rule.severity), (5.0, 1));
+	}
+
+	#[test]
+	fn penalty_for_lifts_when_course_recovers() {
+		let rules = default_rules();
+		assert!(rules.penalty_for(Metric::Grade, 5.1).is_none());
+		assert!(rules.penalty_for(Metric::Grade, 7.0).is_none());
+		assert!(rules.penalty_for(Metric::Attendance, 65.0).is_some());
+		assert!(rules.penalty_for(Metric::Attendance, 66.0).is_none());
+	}
+
+	#[test]
+	fn penalty_for_does_not_mix_metrics() {
+		let rules = default_rules();
+		// 4.0 de asistencia es un castigo de asistencia y no de nota
+		assert_eq!(rules.penalty_for(Metric::Attendance, 4.0).unwrap().metric, Metric::Attendance);
+		assert_eq!(rules.penalty_for(Metric::Grade, 4.0).unwrap().metric, Metric::Grade);
+	}
+
+	#[test]
+	fn default_penalties_stay_below_bonus_tiers() {
+		let rules = default_rules();
+		for tier in &rules.grade_tiers {
+			assert!(rules.penalty_for(Metric::Grade, tier.min).is_none(), "el tramo de {} tiene castigo", tier.min);
+		}
+		for tier in &rules.attendance_tiers {
+			assert!(rules.penalty_for(Metric::Attendance, tier.min).is_none(), "el tramo de {}% tiene castigo", tier.min);
+		}
+	}
+
+	#[test]
+	fn validate_rejects_short_level_table() {
+		let mut rules: XpRules = serde_json::from_str(include_str!("../config/rules.json")).unwrap();
+		rules.levels.max_level = 5;
+		rules.levels.tiers.retain(|tier| tier.min_level <= 5);
+		rules.levels.curve = LevelCurve::Table { exp: vec![100, 300, 600] };
+		assert!(rules.validate().is_err());
+		rules.levels.curve = LevelCurve::Table { exp: vec![100, 300, 600, 1000] };
+		assert!(rules.validate().is_ok());
+	}
+
+	#[test]
+	fn validate_rejects_penalty_overlapping_bonus() {
+		let mut rules = default_rules();
+		rules.penalties.push(PenaltyRule { metric: Metric::Grade, at_most: 6.0, severity: 1, exp: 25 });
+		assert!(rules.validate("default").is_err());
+	}
+}
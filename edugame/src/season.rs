@@ -0,0 +1,96 @@
+use crate::catalog::CourseCatalog;
+use chrono::{DateTime, Utc};
+use rocket::serde::Serialize;
+use rocket::tokio::sync::RwLock;
+use std::sync::Arc;
+
+/*
+ * Temporadas, una por semestre. Cada una tiene su catalogo (y con eso su año/semestre en las urls de ucampus),
+ * su leaderboard y su economia. Al cerrar una temporada se guarda como quedo el leaderboard y lo de cada estudiante,
+ * y se parte de cero con el catalogo nuevo
+ */
+
+#[derive(Debug, Clone)]
+pub struct Season {
+	pub id: String,			// 2025-1
+	pub started_at: DateTime<Utc>,
+	pub catalog: CourseCatalog,
+}
+
+impl Season {
+	pub fn new(catalog: CourseCatalog, started_at: DateTime<Utc>) -> Self {
+		let (year, term) = catalog.term();
+		Season {
+			id: format!("{}-{}", year, term),
+			started_at,
+			catalog,
+		}
+	}
+}
+
+// Como termino cada estudiante en una temporada cerrada
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Standing {
+	pub rank: usize,
+	#[serde(skip_serializing)]
+	pub username: String,		// Solo para buscar las temporadas de un estudiante, el correo no se publica
+	pub name: String,
+	pub level: u16,
+	pub exp: u32,
+	pub coins: u32,
+	pub mean: f32,
+	pub assist: u8,
+	pub bonus: u8,
+	pub penalty: u8,
+	pub achievements: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SeasonArchive {
+	pub id: String,
+	pub started_at: DateTime<Utc>,
+	pub closed_at: DateTime<Utc>,
+	pub standings: Vec<Standing>,	// En el orden del leaderboard al cerrar
+}
+
+pub struct Seasons {
+	pub current: Season,
+	pub archives: Vec<SeasonArchive>,	// La mas antigua primero
+}
+
+impl Seasons {
+	pub fn new(catalog: CourseCatalog) -> Self {
+		Seasons {
+			current: Season::new(catalog, Utc::now()),
+			archives: vec![],
+		}
+	}
+
+	pub fn archive(&self, id: &str) -> Option<&SeasonArchive> {
+		self.archives.iter().find(|archive| archive.id == id)
+	}
+
+	/*
+	 * Guarda la temporada actual con sus resultados y empieza la siguiente con el catalogo nuevo.
+	 * No se puede volver a abrir un semestre que ya se jugo, ni "cerrar" hacia el mismo semestre
+	 */
+	pub fn close(&mut self, standings: Vec<Standing>, next_catalog: CourseCatalog, now: DateTime<Utc>) -> Result<&SeasonArchive, String> {
+		let next = Season::new(next_catalog, now);
+		if next.id == self.current.id || self.archive(&next.id).is_some() {
+			return Err(format!("La temporada {} ya se jugo o esta en curso", next.id));
+		}
+
+		let finished = std::mem::replace(&mut self.current, next);
+		self.archives.push(SeasonArchive {
+			id: finished.id,
+			started_at: finished.started_at,
+			closed_at: now,
+			standings,
+		});
+		Ok(self.archives.last().unwrap())
+	}
+}
+
+pub type SeasonState = Arc<RwLock<Seasons>>;
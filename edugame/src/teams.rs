@@ -0,0 +1,174 @@
+use crate::rules::{TeamGoal, TeamMetric, TeamRules};
+use chrono::{DateTime, Duration, Utc};
+use rocket::serde::Serialize;
+use rocket::tokio::sync::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/*
+ * Equipos, salen del Lobby de local_code3.rs que juntaba a los Student en un Vec.
+ * Aqui el lobby guarda solo los correos de los integrantes, los datos de cada uno se siguen leyendo de StudentStorage.
+ * Un estudiante esta en un solo equipo a la vez, lo puede crear, unirse o lo puede asignar un profe
+ */
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Lobby {
+	pub id: String,				// El nombre en minusculas y con guiones, "Los Cracks" -> los-cracks
+	pub name: String,
+	#[serde(skip_serializing)]
+	pub members: Vec<String>,	// Correos, no se publican
+	pub created_at: DateTime<Utc>,
+	pub paid_goals: HashMap<String, DateTime<Utc>>,	// id de la meta -> cuando el equipo la cumplio por primera vez
+	#[serde(skip_serializing)]
+	pub joined_at: HashMap<String, DateTime<Utc>>,	// correo -> cuando entro al equipo
+}
+
+impl Lobby {
+	/*
+	 * Los integrantes que llevan al menos min_membership_hours en el equipo, solo ellos suman para las metas y las cobran.
+	 * Asi no sirve meterse a un equipo justo antes de que cumpla una meta, ni armar equipos para cobrar al tiro
+	 */
+	pub fn settled_members(&self, now: DateTime<Utc>, rules: &TeamRules) -> Vec<&String> {
+		let min = Duration::hours(rules.min_membership_hours as i64);
+		self.members.iter()
+			.filter(|username| self.joined_at.get(*username).is_some_and(|joined| now - *joined >= min))
+			.collect()
+	}
+}
+
+// Lo que se calcula de un equipo con los datos de sus integrantes
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TeamTotals {
+	pub members: usize,
+	pub total_exp: u32,
+	pub average_attendance: f32,
+	pub score: f32,
+}
+
+impl TeamTotals {
+	// Cada integrante como (exp, asistencia)
+	pub fn from_members(members: &[(u32, u8)], rules: &TeamRules) -> Self {
+		if members.is_empty() {
+			return TeamTotals::default();
+		}
+		let total_exp: u32 = members.iter().map(|(exp, _)| exp).sum();
+		let total_attendance: f32 = members.iter().map(|(_, assist)| *assist as f32).sum();
+		TeamTotals {
+			members: members.len(),
+			total_exp,
+			average_attendance: total_attendance / members.len() as f32,
+			score: total_exp as f32 * rules.exp_weight + total_attendance * rules.attendance_weight,
+		}
+	}
+
+	pub fn value(&self, metric: TeamMetric) -> f32 {
+		match metric {
+			TeamMetric::TotalExp => self.total_exp as f32,
+			TeamMetric::AverageAttendance => self.average_attendance,
+			TeamMetric::Score => self.score,
+		}
+	}
+
+	pub fn reached(&self, goal: &TeamGoal) -> bool {
+		self.value(goal.metric) >= goal.target
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct Teams {
+	pub lobbies: HashMap<String, Lobby>,
+}
+
+pub type TeamStorage = Arc<RwLock<Teams>>;
+
+fn team_id(name: &str) -> String {
+	name.split_whitespace()
+		.map(|word| word.to_lowercase())
+		.collect::<Vec<_>>()
+		.join("-")
+}
+
+// Devuelve el nombre limpio (sin espacios de mas) y su id
+fn clean_name(name: &str) -> Result<(String, String), String> {
+	let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+	let id = team_id(&name);
+	if id.is_empty() || name.chars().count() > 40 {
+		return Err("El nombre del equipo tiene que tener entre 1 y 40 caracteres".to_string());
+	}
+	// El nombre sale tal cual en la pagina de equipos, solo letras, numeros, espacios, - y _
+	if !name.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_') {
+		return Err("El nombre del equipo solo puede tener letras, numeros, espacios, - y _".to_string());
+	}
+	Ok((name, id))
+}
+
+impl Teams {
+	pub fn team_of(&self, username: &str) -> Option<&Lobby> {
+		self.lobbies.values().find(|lobby| lobby.members.iter().any(|m| m == username))
+	}
+
+	pub fn create(&mut self, name: &str, username: &str, rules: &TeamRules) -> Result<&Lobby, String> {
+		let (name, id) = clean_name(name)?;
+		if self.lobbies.contains_key(&id) {
+			return Err(format!("Ya existe un equipo llamado {}", name));
+		}
+		if let Some(current) = self.team_of(username) {
+			return Err(format!("Ya estas en el equipo {}, sal de ese primero", current.name));
+		}
+		self.lobbies.insert(id.clone(), Lobby {
+			id: id.clone(),
+			name,
+			members: vec![],
+			created_at: Utc::now(),
+			paid_goals: HashMap::new(),
+			joined_at: HashMap::new(),
+		});
+		self.join(&id, username, rules)
+	}
+
+	pub fn join(&mut self, id: &str, username: &str, rules: &TeamRules) -> Result<&Lobby, String> {
+		if let Some(current) = self.team_of(username) {
+			return Err(format!("Ya estas en el equipo {}, sal de ese primero", current.name));
+		}
+		let lobby = self.lobbies.get_mut(id).ok_or("Equipo no encontrado".to_string())?;
+		if lobby.members.len() >= rules.max_members {
+			return Err(format!("El equipo {} esta lleno ({} integrantes)", lobby.name, rules.max_members));
+		}
+		lobby.members.push(username.to_string());
+		lobby.joined_at.insert(username.to_string(), Utc::now());
+		Ok(lobby)
+	}
+
+	// Sale de su equipo, si queda vacio se borra. Devuelve el nombre del equipo
+	pub fn leave(&mut self, username: &str) -> Result<String, String> {
+		let lobby = self.lobbies.values_mut()
+			.find(|lobby| lobby.members.iter().any(|m| m == username))
+			.ok_or("No estas en ningun equipo".to_string())?;
+		lobby.members.retain(|m| m != username);
+		lobby.joined_at.remove(username);
+		let (id, name, empty) = (lobby.id.clone(), lobby.name.clone(), lobby.members.is_empty());
+		if empty {
+			self.lobbies.remove(&id);
+		}
+		Ok(name)
+	}
+
+	// Lo que hace un profe: saca al estudiante de donde este y lo pone en el equipo, si el equipo no existe se crea
+	pub fn assign(&mut self, username: &str, team_name: &str, rules: &TeamRules) -> Result<&Lobby, String> {
+		let (_, id) = clean_name(team_name)?;
+		if self.team_of(username).is_some_and(|lobby| lobby.id == id) {
+			return Ok(&self.lobbies[&id]);
+		}
+		if self.lobbies.get(&id).is_some_and(|lobby| lobby.members.len() >= rules.max_members) {
+			return Err(format!("El equipo {} esta lleno ({} integrantes)", team_name, rules.max_members));
+		}
+		let _ = self.leave(username);
+		if self.lobbies.contains_key(&id) {
+			self.join(&id, username, rules)
+		} else {
+			self.create(team_name, username, rules)
+		}
+	}
+}
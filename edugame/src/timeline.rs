@@ -0,0 +1,93 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use rocket::serde::{Deserialize, Serialize};
+
+/*
+ * Historial de cada estudiante: cada vez que cambia la experiencia o las monedas queda un evento con la fecha,
+ * cuanto cambio y como quedo. Sirve para ver el progreso en el index y para /students/<username>/timeline.
+ * Ese endpoint no pide contraseña, asi que las descripciones no llevan notas, promedios ni asistencias
+ */
+
+// Para que el historial no crezca para siempre, se botan los mas antiguos
+pub const MAX_EVENTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum EventKind {
+	XpGrant,	// Experiencia por notas/asistencia, premio por puesto, etc
+	Penalty,	// Castigo por promedio o asistencia bajo el limite
+	LevelUp,
+	Purchase,
+	Game,		// Coinflip y slots, gane o pierda
+	TeamGoal,	// Monedas por una meta de equipo cumplida
+	CheckIn,	// Check-in diario
+}
+
+impl EventKind {
+	pub fn parse(value: &str) -> Option<Self> {
+		match value {
+			"xp_grant" => Some(EventKind::XpGrant),
+			"penalty" => Some(EventKind::Penalty),
+			"level_up" => Some(EventKind::LevelUp),
+			"purchase" => Some(EventKind::Purchase),
+			"game" => Some(EventKind::Game),
+			"team_goal" => Some(EventKind::TeamGoal),
+			"check_in" => Some(EventKind::CheckIn),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TimelineEvent {
+	pub at: DateTime<Utc>,
+	pub kind: EventKind,
+	pub description: String,
+	pub exp_delta: i64,
+	pub coins_delta: i64,
+	// Como quedo el estudiante despues del evento
+	pub exp: u32,
+	pub coins: u32,
+	pub level: u16,
+}
+
+// Filtros de /students/<username>/timeline, todos opcionales
+#[derive(Debug, Default)]
+pub struct TimelineFilter {
+	pub kinds: Vec<EventKind>,
+	pub from: Option<DateTime<Utc>>,
+	pub to: Option<DateTime<Utc>>,
+}
+
+impl TimelineFilter {
+	/*
+	 * kind acepta varios separados por coma (purchase,game), from y to aceptan una fecha (2025-04-01) o fecha y hora RFC 3339.
+	 * Con solo la fecha, to incluye el dia completo
+	 */
+	pub fn parse(kind: Option<&str>, from: Option<&str>, to: Option<&str>) -> Result<Self, String> {
+		let mut kinds = vec![];
+		for value in kind.unwrap_or_default().split(',').map(str::trim).filter(|v| !v.is_empty()) {
+			kinds.push(EventKind::parse(value).ok_or(format!("Tipo de evento desconocido: {}", value))?);
+		}
+		Ok(TimelineFilter {
+			kinds,
+			from: from.map(|v| parse_date(v, NaiveTime::MIN)).transpose()?,
+			to: to.map(|v| parse_date(v, NaiveTime::from_hms_milli_opt(23, 59, 59, 999).unwrap())).transpose()?,
+		})
+	}
+
+	pub fn matches(&self, event: &TimelineEvent) -> bool {
+		(self.kinds.is_empty() || self.kinds.contains(&event.kind))
+			&& self.from.is_none_or(|from| event.at >= from)
+			&& self.to.is_none_or(|to| event.at <= to)
+	}
+}
+
+fn parse_date(value: &str, time_if_date_only: NaiveTime) -> Result<DateTime<Utc>, String> {
+	if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+		return Ok(at.with_timezone(&Utc));
+	}
+	NaiveDate::parse_from_str(value, "%Y-%m-%d")
+		.map(|date| date.and_time(time_if_date_only).and_utc())
+		.map_err(|_| format!("Fecha invalida: {}", value))
+}
@@ -0,0 +1,512 @@
+use reqwest::{Client, StatusCode};
+use rocket::http::Status;
+use rocket::serde::{Deserialize, Serialize};
+use rocket::futures::future::join_all;
+use rocket::tokio::sync::{RwLock, Semaphore};
+use rocket::tokio::time::{Instant, sleep, timeout_at};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::catalog::{Course, CourseCatalog, discover_courses};
+use crate::logging::redact;
+use crate::parser::{ClassSession, GradeEntry, parse_attendance, parse_grades};
+
+/*
+ * Todo lo que habla con ucampus. El resto del programa solo ve GradeSource, asi se puede cambiar
+ * ucampus de verdad por el mock_ucampus (src/bin/mock_ucampus.rs) y probar /scrape sin internet ni credenciales
+ */
+
+pub const UCAMPUS_URL: &str = "https://ucampus.uahurtado.cl";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ApiResponse {
+	pub status: i32,
+	pub u: Option<String>,
+}
+
+// Lo que se saca de ucampus por cada curso del catalogo, antes eran 4 vectores y 4 asistencias sueltas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CourseData {
+	pub code: String,
+	pub grades: Vec<GradeEntry>,
+	pub attendance: f32,
+	pub sessions: Vec<ClassSession>,
+}
+
+// Curso que no se pudo sacar, se le muestra al estudiante junto con el resto de los datos
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FailedCourse {
+	pub code: String,
+	pub name: String,
+	pub reason: String,
+}
+
+// Lo que devuelve una fuente de notas despues de loguear al estudiante
+#[derive(Debug, Clone)]
+pub struct ScrapedData {
+	pub name: Option<String>,				// None si ninguna pagina de notas alcanzo a llegar
+	pub courses: Vec<CourseData>,
+	pub failed: Vec<FailedCourse>,			// Cursos que fallaron o no alcanzaron a llegar antes del plazo
+	pub first_error: Option<ScrapeError>,	// El error del primer curso que fallo
+}
+
+/*
+ * Todo lo que puede salir mal al scrapear, cada uno con su status http y un mensaje para mostrar en el login,
+ * antes todo era Box<dyn Error> y un 500, y no se sabia si era la contraseña o ucampus que estaba caido
+ */
+#[derive(Debug, Clone)]
+pub enum ScrapeError {
+	BadCredentials,
+	MissingSessionCookie,
+	PermissionDenied { course: String },
+	UpstreamTimeout,
+	Unavailable(String),	// No se pudo conectar o ucampus respondio con error
+	Parse(String),			// Ucampus respondio algo que no sabemos leer
+	SeasonChanged,			// Se cerro la temporada mientras se scrapeaba, el resultado es de la anterior
+}
+
+impl ScrapeError {
+	pub fn status(&self) -> Status {
+		match self {
+			ScrapeError::BadCredentials => Status::Unauthorized,
+			ScrapeError::MissingSessionCookie => Status::BadGateway,
+			ScrapeError::PermissionDenied { .. } => Status::Forbidden,
+			ScrapeError::UpstreamTimeout => Status::GatewayTimeout,
+			ScrapeError::Unavailable(_) => Status::ServiceUnavailable,
+			ScrapeError::Parse(_) => Status::BadGateway,
+			ScrapeError::SeasonChanged => Status::Conflict,
+		}
+	}
+
+	// Lo que ve el estudiante, el detalle tecnico queda en el Display
+	pub fn user_message(&self) -> String {
+		match self {
+			ScrapeError::BadCredentials => "Error. Verifica tu usuario y contraseña.".to_string(),
+			ScrapeError::MissingSessionCookie => "U-Campus no entregó una sesión, intenta de nuevo en unos minutos.".to_string(),
+			ScrapeError::PermissionDenied { course } => format!("No tienes acceso a ninguna sección del curso {}.", course),
+			ScrapeError::UpstreamTimeout => "U-Campus se demoró demasiado en responder, intenta más tarde.".to_string(),
+			ScrapeError::Unavailable(_) => "U-Campus no está disponible en este momento.".to_string(),
+			ScrapeError::Parse(_) => "No pudimos leer tus datos de U-Campus, puede que hayan cambiado la página.".to_string(),
+			ScrapeError::SeasonChanged => "La temporada cambió mientras cargábamos tus datos, vuelve a ingresar.".to_string(),
+		}
+	}
+}
+
+impl std::fmt::Display for ScrapeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ScrapeError::BadCredentials => write!(f, "credenciales invalidas"),
+			ScrapeError::MissingSessionCookie => write!(f, "no llego la cookie _ucampus"),
+			ScrapeError::PermissionDenied { course } => write!(f, "sin permisos en ninguna seccion de {}", course),
+			ScrapeError::UpstreamTimeout => write!(f, "timeout esperando a ucampus"),
+			ScrapeError::Unavailable(detail) => write!(f, "ucampus no disponible: {}", detail),
+			ScrapeError::Parse(detail) => write!(f, "no se pudo leer la respuesta de ucampus: {}", detail),
+			ScrapeError::SeasonChanged => write!(f, "la temporada se cerro durante el scrapeo"),
+		}
+	}
+}
+
+impl std::error::Error for ScrapeError {}
+
+// Para poder seguir usando ? con reqwest
+impl From<reqwest::Error> for ScrapeError {
+	fn from(e: reqwest::Error) -> Self {
+		if e.is_timeout() {
+			ScrapeError::UpstreamTimeout
+		} else if e.is_decode() {
+			ScrapeError::Parse(e.to_string())
+		} else {
+			ScrapeError::Unavailable(e.to_string())
+		}
+	}
+}
+
+#[rocket::async_trait]
+pub trait GradeSource: Send + Sync {
+	async fn fetch_student(&self, username: &str, password: &str, catalog: &CourseCatalog) -> Result<ScrapedData, ScrapeError>;
+}
+
+// Hasta que seccion se prueba cuando el curso no trae las suyas o ninguna de esas funciona
+const MAX_SECTION_PROBE: u8 = 9;
+
+// Tiempos del scrapeo, cada peticion tiene su timeout y todo el login tiene un plazo total
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(8);
+const SCRAPE_DEADLINE: Duration = Duration::from_secs(25);
+const MAX_RETRIES: u32 = 2;
+const RETRY_BACKOFF: Duration = Duration::from_millis(300);
+const MAX_PARALLEL_COURSES: usize = 3;
+const NO_PERMISSION_MARKER: &str = "No tienes permisos para ver esta";
+
+/*
+ * Cache de secciones por estudiante, username -> (codigo del curso -> seccion), asi en el siguiente login
+ * no se vuelve a probar seccion por seccion. Igual que el StudentStorage muere cuando se apaga el servidor
+ */
+type SectionCache = Arc<RwLock<HashMap<String, HashMap<String, u8>>>>;
+
+// La implementacion de verdad, base_url es https://ucampus.uahurtado.cl salvo que se apunte al mock
+pub struct UCampus {
+	base_url: String,
+	section_cache: SectionCache,
+}
+
+impl UCampus {
+	pub fn new(base_url: &str) -> Self {
+		UCampus {
+			base_url: base_url.trim_end_matches('/').to_string(),
+			section_cache: Arc::new(RwLock::new(HashMap::new())),
+		}
+	}
+
+	// EDUGAME_UCAMPUS_URL=http://127.0.0.1:8001 para usar el mock_ucampus
+	pub fn from_env() -> Self {
+		Self::new(&std::env::var("EDUGAME_UCAMPUS_URL").unwrap_or(UCAMPUS_URL.to_string()))
+	}
+
+	/*
+	 * Encuentra la seccion real del estudiante en el curso, el formato de la url es uah/[año]/[semestre]/CSIXXXX/[seccion]/notas/alumno
+	 * Primero la que estaba en cache, despues las del curso y al final todas hasta MAX_SECTION_PROBE.
+	 * Una respuesta que no es 2xx (404 de una seccion que no existe, 401 con la sesion vencida) tampoco es la seccion.
+	 * Devuelve la seccion junto con la pagina de notas para no pedirla dos veces, fetch_course la guarda en cache
+	 * recien cuando la pagina se pudo leer
+	 */
+	async fn resolve_section(&self, client: &Client, course: &Course, username: &str) -> Result<(u8, String), ScrapeError> {
+		let cached = self.section_cache.read().await
+			.get(username)
+			.and_then(|courses| courses.get(&course.code))
+			.copied();
+
+		let mut candidates: Vec<u8> = vec![];
+		for section in cached.into_iter().chain(course.sections.iter().copied()).chain(1..=MAX_SECTION_PROBE) {
+			if !candidates.contains(&section) {
+				candidates.push(section);
+			}
+		}
+
+		for section in candidates {
+			let url = format!("{}/{}/notas/alumno", self.base_url, course.path_for_section(section));
+			let (status, text) = get_page_with_status(client, &url).await?;
+			if !status.is_success() || text.contains(NO_PERMISSION_MARKER) {
+				continue; //En caso de que no este en esta seccion prueba con la siguiente
+			}
+			return Ok((section, text));
+		}
+
+		// Mejor avisar que devolver el curso en blanco con nota 0 y asistencia 0%
+		Err(ScrapeError::PermissionDenied { course: format!("{} ({})", course.name, course.code) })
+	}
+}
+
+#[rocket::async_trait]
+impl GradeSource for UCampus {
+	async fn fetch_student(&self, username: &str, password: &str, catalog: &CourseCatalog) -> Result<ScrapedData, ScrapeError> {
+
+		/*
+		 * Armado de el cliente para las peticiones web de ucampus PD: Todo lo saque y probe con postman interceptor, despues con flask en python y ahí lo traduje a oxido
+		 * donde su equivalencia es reqwest, tambien usamos tokio y serde para las respuestas en json
+	 	 */
+
+		let deadline = Instant::now() + SCRAPE_DEADLINE;
+		let client = Client::builder()
+			.cookie_store(true)
+			.timeout(REQUEST_TIMEOUT)
+			.build()?;
+
+		let cookie_monster = client
+			.get(format!("{}/", self.base_url))
+			.send()
+			.await?
+			.error_for_status()?; // Un 5xx de ucampus es que esta caido, no que falte la cookie
+
+		/*
+		 * Extraigo las cookies que en las otras peticiones almacenan la sesion
+	 	 */
+
+		let cookies = cookie_monster.cookies().collect::<Vec<_>>();
+		let sess_cookie = cookies
+			.iter()
+			.find(|c| c.name() == "_ucampus")
+			.ok_or(ScrapeError::MissingSessionCookie)?; //No deberia pasar NUNCA a no ser que pase un problema
+
+		let mut login_data = HashMap::new();
+		login_data.insert("servicio", "ucampus");
+		login_data.insert("debug", "0");
+		login_data.insert("_sess", sess_cookie.value());
+		login_data.insert("_LB", "uah02-int");
+		login_data.insert("lang", "es");
+		login_data.insert("username", username);
+		login_data.insert("password", password);
+		login_data.insert("recordar", "1");
+
+		let login_response = client
+			.post(format!("{}/auth/api", self.base_url))
+			.header("User-Agent", "GamificationEngineRuntime/7.44.1") //No funciona y llega el correo a quien lo usa que ingresaron de un dispositivo unknown
+			.form(&login_data)
+			.send()
+			.await?
+			.error_for_status()?;
+
+		let login_json: ApiResponse = login_response.json().await?;
+
+		if login_json.status != 200 {
+			tracing::info!(status = login_json.status, "ucampus rechazo el login");
+			return Err(ScrapeError::BadCredentials);
+		}
+
+		//Si login_json.u tiene algo entonces se guarda en main_url, la no existencia de valores nulos nos deja con el uso de Some
+		// el uso de some lo utilizo para varias veces luego cuando tenga que extraer las notas de las materias
+		let portal_url = login_json.u.unwrap_or(format!("{}/", self.base_url));
+		let portal_text = get_page(&client, &portal_url).await?;
+
+		// Los cursos salen del portal del alumno, si no se encuentra ninguno (cambio el html?) se usa el catalogo completo como antes
+		let mut enrolled = discover_courses(&portal_text, catalog);
+		if enrolled.is_empty() {
+			enrolled = catalog.courses.clone();
+		}
+
+		/*
+		 * Antes eran 8 peticiones una detras de otra, ahora los cursos se piden en paralelo (maximo MAX_PARALLEL_COURSES a la vez)
+		 * y con un plazo total, el curso que no alcanza o falla queda en failed y el resto se entrega igual
+		 */
+		let client = &client;
+		let permits = Semaphore::new(MAX_PARALLEL_COURSES);
+		let mut pending = vec![];
+		for course in &enrolled {
+			let permits = &permits;
+			pending.push(async move {
+				let result = match timeout_at(deadline, async {
+					let _permit = permits.acquire().await.map_err(|e| ScrapeError::Unavailable(e.to_string()))?;
+					self.fetch_course(client, course, username).await
+				}).await {
+					Ok(result) => result,
+					Err(_) => Err(ScrapeError::UpstreamTimeout),
+				};
+				(course, result)
+			});
+		}
+		let results = join_all(pending).await; // join_all respeta el orden de los cursos
+
+		let mut courses = vec![];
+		let mut failed = vec![];
+		let mut first_error = None;
+		let mut name = None;
+
+		for (course, result) in results {
+			match result {
+				Ok((data, course_name)) => {
+					if name.is_none() {
+						name = course_name;
+					}
+					courses.push(data);
+				}
+				Err(e) => {
+					tracing::warn!(course = %course.code, error = %e, "no se pudo scrapear el curso");
+					failed.push(FailedCourse {
+						code: course.code.clone(),
+						name: course.name.clone(),
+						reason: e.user_message(),
+					});
+					first_error.get_or_insert(e);
+				}
+			}
+		}
+		tracing::info!(courses = courses.len(), failed = failed.len(), "scrapeo terminado");
+
+		// Aunque no haya llegado ningun curso el login fue valido, main.rs decide si hay cache para mostrar o se devuelve first_error
+		Ok(ScrapedData {
+			name,
+			courses,
+			failed,
+			first_error,
+		})
+	}
+}
+
+impl UCampus {
+	// Notas y asistencia de un curso, junto con el nombre del alumno si sale en la pagina
+	async fn fetch_course(&self, client: &Client, course: &Course, username: &str) -> Result<(CourseData, Option<String>), ScrapeError> {
+		let (section, grades_text) = self.resolve_section(client, course, username).await?;
+		let attendance_url = format!("{}/{}/asistencias2/", self.base_url, course.path_for_section(section));
+		let attendance_text = get_page(client, &attendance_url).await?;
+
+		// Ya no hay limite de notas por curso, el formulario vacio del examen queda como una evaluacion sin nota
+		let grades = parse_grades(&grades_text);
+		if grades.len() != course.expected_evaluations {
+			tracing::debug!(course = %course.code, expected = course.expected_evaluations, found = grades.len(), "cantidad de evaluaciones distinta a la del catalogo");
+		}
+
+		let attendance = parse_attendance(&attendance_text);
+		let name = extract_nth_between(&grades_text, "alias: '", "',", 1); //De aquí sale el nombre del estudiante
+
+		// Una pagina de notas sin nombre ni evaluaciones no es una pagina de notas, mejor avisar que inventar un curso vacio
+		if name.is_none() && grades.is_empty() {
+			return Err(ScrapeError::Parse(format!("la pagina de notas de {} no tiene el formato esperado", course.code)));
+		}
+
+		// La seccion queda en cache solo ahora que su pagina de notas se pudo leer
+		self.section_cache.write().await
+			.entry(username.to_string())
+			.or_default()
+			.insert(course.code.clone(), section);
+
+		let data = CourseData {
+			code: course.code.clone(),
+			grades,
+			attendance: attendance.percentage,
+			sessions: attendance.sessions,
+		};
+
+		// Las notas y la asistencia salen ocultas salvo que se active EDUGAME_LOG_SENSITIVE
+		tracing::debug!(course = %data.code, section, grades = %redact(&data.grades), attendance = %redact(&data.attendance), sessions = data.sessions.len(), "curso scrapeado");
+		Ok((data, name))
+	}
+}
+
+/*
+ * GET con reintentos, solo se reintenta lo que puede ser pasajero (timeout, conexion, 5xx) esperando el doble cada vez.
+ * Ucampus responde 200 con el mensaje de permisos cuando no es la seccion, asi que los 4xx se dejan pasar como texto
+ */
+async fn get_page(client: &Client, url: &str) -> Result<String, ScrapeError> {
+	get_page_with_status(client, url).await.map(|(_, text)| text)
+}
+
+// Lo mismo pero con el status, para resolve_section que no puede tomar un 404 o 401 como la seccion
+async fn get_page_with_status(client: &Client, url: &str) -> Result<(StatusCode, String), ScrapeError> {
+	let mut attempt = 0;
+	loop {
+		let result: Result<(StatusCode, String), reqwest::Error> = async {
+			let response = client.get(url).send().await?;
+			let response = if response.status().is_server_error() { response.error_for_status()? } else { response };
+			let status = response.status();
+			Ok((status, response.text().await?))
+		}.await;
+
+		match result {
+			Ok(page) => return Ok(page),
+			Err(e) if attempt < MAX_RETRIES && is_transient(&e) => {
+				tracing::debug!(attempt, error = %e, "reintentando peticion a ucampus");
+				sleep(RETRY_BACKOFF * 2u32.pow(attempt)).await;
+				attempt += 1;
+			}
+			Err(e) => return Err(e.into()),
+		}
+	}
+}
+
+fn is_transient(e: &reqwest::Error) -> bool {
+	e.is_timeout() || e.is_connect() || e.status().is_some_and(|status| status.is_server_error())
+}
+
+/*
+* def extract_between_r( s, first, last ):
+*	 start = s.rindex( first ) + len( first )
+*	 end = s.rindex( last, start )
+*	 return s[start:end]
+*/
+// Lo que en python hacia con assitencia2 = assaignment2Data.split("<th>Asistencia")[1].split("%</h1>")[0][-3:].replace(">","") aquí tengo que usar esta funcion, nacida a base de find_between_r de https://stackoverflow.com/questions/3368969/find-string-between-two-substrings
+
+fn extract_nth_between(text: &str, start: &str, end: &str, n: usize) -> Option<String> {
+	let mut current_pos = 0;
+	for i in 1..=n {
+		if let Some(start_idx) = text[current_pos..].find(start) {
+			let start_pos = start_idx + current_pos + start.len();
+			if let Some(end_idx) = text[start_pos..].find(end) {
+				let end_pos = end_idx + start_pos;
+				if i == n {
+					return Some(text[start_pos..end_pos].to_string());
+				}
+				current_pos = end_pos + end.len();
+			} else {
+				return None;
+			}
+		} else {
+			return None;
+		}
+	}
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rocket::tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use rocket::tokio::net::TcpListener;
+
+	const NOTAS: &str = include_str!("../fixtures/ucampus/demo/CSI0165/notas.html");
+	const NO_PERMISSION_PAGE: &str = "<html><body><h2>No tienes permisos para ver esta página</h2></body></html>";
+
+	// Servidor HTTP minimo que responde cada ruta con su status y cuerpo, lo que no esta es 404
+	async fn serve(routes: Vec<(String, u16, &'static str)>) -> String {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		rocket::tokio::spawn(async move {
+			loop {
+				let Ok((mut socket, _)) = listener.accept().await else { return };
+				let mut buffer = vec![0u8; 4096];
+				let read = socket.read(&mut buffer).await.unwrap_or(0);
+				let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+				let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+				let (status, body) = routes.iter()
+					.find(|(route, _, _)| *route == path)
+					.map(|(_, status, body)| (*status, *body))
+					.unwrap_or((404, "no existe"));
+				let response = format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+				let _ = socket.write_all(response.as_bytes()).await;
+			}
+		});
+		format!("http://{}", addr)
+	}
+
+	fn course() -> Course {
+		Course { code: "CSI0165".to_string(), name: "Algebra Lineal".to_string(), year: 2025, term: 1, sections: vec![1, 2], expected_evaluations: 3 }
+	}
+
+	async fn cached_section(ucampus: &UCampus) -> Option<u8> {
+		ucampus.section_cache.read().await.get("demo").and_then(|courses| courses.get("CSI0165")).copied()
+	}
+
+	#[rocket::async_test]
+	async fn resolve_section_skips_error_pages() {
+		let base = serve(vec![
+			("/uah/2025/1/CSI0165/1/notas/alumno".to_string(), 401, "sesion vencida"),
+			("/uah/2025/1/CSI0165/3/notas/alumno".to_string(), 200, NOTAS),
+		]).await;
+		let ucampus = UCampus::new(&base);
+		let (section, text) = ucampus.resolve_section(&Client::new(), &course(), "demo").await.unwrap();
+		assert_eq!(section, 3); // La 1 es 401, la 2 es 404
+		assert_eq!(text, NOTAS);
+	}
+
+	#[rocket::async_test]
+	async fn resolve_section_without_any_page_is_permission_denied() {
+		let base = serve(vec![
+			("/uah/2025/1/CSI0165/1/notas/alumno".to_string(), 200, NO_PERMISSION_PAGE),
+		]).await;
+		let ucampus = UCampus::new(&base);
+		let result = ucampus.resolve_section(&Client::new(), &course(), "demo").await;
+		assert!(matches!(result, Err(ScrapeError::PermissionDenied { .. })));
+		assert_eq!(cached_section(&ucampus).await, None);
+	}
+
+	#[rocket::async_test]
+	async fn section_cached_only_after_page_parses() {
+		let base = serve(vec![
+			("/uah/2025/1/CSI0165/1/notas/alumno".to_string(), 200, "<html><body>mantencion</body></html>"),
+		]).await;
+		let ucampus = UCampus::new(&base);
+		let result = ucampus.fetch_course(&Client::new(), &course(), "demo").await;
+		assert!(matches!(result, Err(ScrapeError::Parse(_))));
+		assert_eq!(cached_section(&ucampus).await, None);
+
+		let base = serve(vec![
+			("/uah/2025/1/CSI0165/2/notas/alumno".to_string(), 200, NOTAS),
+		]).await;
+		let ucampus = UCampus::new(&base);
+		let (data, _) = ucampus.fetch_course(&Client::new(), &course(), "demo").await.unwrap();
+		assert!(!data.grades.is_empty());
+		assert_eq!(cached_section(&ucampus).await, Some(2));
+	}
+}